@@ -0,0 +1,313 @@
+use crate::types::math::{Aabb, Direction, Position, Vec3};
+
+const LEAF_SIZE: usize = 2;
+
+struct Node {
+    bounds: Aabb,
+    /// Leaves cover `items[start..start + count]`, interior nodes have
+    /// `count == 0` and their children at `start` and `start + 1`.
+    start: usize,
+    count: usize,
+}
+
+/// Bounding-volume hierarchy over item indices.
+///
+/// Items without bounds (infinite implicit surfaces, for example) can't be
+/// placed in the tree and are tested on every query instead.
+pub struct Bvh {
+    nodes: Vec<Node>,
+    items: Vec<usize>,
+    /// Bounds of every item by index, so leaves can skip the ones missed.
+    bounds: Vec<Aabb>,
+    unbounded: Vec<usize>,
+}
+
+impl Bvh {
+    pub fn new(bounds: &[Option<Aabb>]) -> Bvh {
+        let mut items = Vec::new();
+        let mut unbounded = Vec::new();
+        for (index, aabb) in bounds.iter().enumerate() {
+            match aabb {
+                Some(_) => items.push(index),
+                None => unbounded.push(index),
+            }
+        }
+
+        let bounds: Vec<Aabb> = bounds.iter().map(|b| b.unwrap_or(EMPTY)).collect();
+        let mut bvh = Bvh {
+            nodes: Vec::new(),
+            items,
+            bounds,
+            unbounded,
+        };
+        if !bvh.items.is_empty() {
+            bvh.nodes.push(Node {
+                bounds: EMPTY,
+                start: 0,
+                count: 0,
+            });
+            bvh.build(0, 0, bvh.items.len());
+        }
+        bvh
+    }
+
+    fn build(&mut self, node: usize, start: usize, end: usize) {
+        let bounds = &self.bounds;
+        let items = &mut self.items[start..end];
+        let node_bounds = items
+            .iter()
+            .map(|&i| bounds[i])
+            .reduce(Aabb::union)
+            .unwrap();
+        self.nodes[node].bounds = node_bounds;
+
+        if items.len() <= LEAF_SIZE {
+            self.nodes[node].start = start;
+            self.nodes[node].count = items.len();
+            return;
+        }
+
+        // median split along the longest axis of the centroids
+        let centroids = items
+            .iter()
+            .map(|&i| {
+                let center = bounds[i].center();
+                Aabb::new(center, center)
+            })
+            .reduce(Aabb::union)
+            .unwrap();
+        let extent = centroids.max - centroids.min;
+        let axis = if extent.x >= extent.y && extent.x >= extent.z {
+            0
+        } else if extent.y >= extent.z {
+            1
+        } else {
+            2
+        };
+        let key = |i: &usize| {
+            let center = bounds[*i].center();
+            [center.x, center.y, center.z][axis]
+        };
+        let middle = items.len() / 2;
+        items.select_nth_unstable_by(middle, |a, b| key(a).total_cmp(&key(b)));
+
+        let left = self.nodes.len();
+        for _ in 0..2 {
+            self.nodes.push(Node {
+                bounds: EMPTY,
+                start: 0,
+                count: 0,
+            });
+        }
+        self.nodes[node].start = left;
+        self.build(left, start, start + middle);
+        self.build(left + 1, start + middle, end);
+    }
+
+    /// Finds the closest item hit along a ray.
+    ///
+    /// `test` is called with an item index and the distance of the closest hit
+    /// so far, and returns the distance and payload of its own hit, if any.
+    pub fn closest<T>(
+        &self,
+        origin: Position,
+        direction: Direction,
+        mut test: impl FnMut(usize, f64) -> Option<(f64, T)>,
+    ) -> Option<(f64, T)> {
+        let mut result: Option<(f64, T)> = None;
+        let mut consider = |index: usize, result: &mut Option<(f64, T)>| {
+            let limit = result.as_ref().map_or(f64::INFINITY, |(d, _)| *d);
+            if let Some((distance, value)) = test(index, limit) {
+                if distance < limit {
+                    *result = Some((distance, value));
+                }
+            }
+        };
+
+        for &index in self.unbounded.iter() {
+            consider(index, &mut result);
+        }
+        if self.nodes.is_empty() {
+            return result;
+        }
+
+        let mut stack = vec![0];
+        while let Some(node) = stack.pop() {
+            let node = &self.nodes[node];
+            let limit = result.as_ref().map_or(f64::INFINITY, |(d, _)| *d);
+            if node
+                .bounds
+                .entry_distance(origin, direction, limit)
+                .is_none()
+            {
+                continue;
+            }
+            if node.count > 0 {
+                for &index in self.items[node.start..node.start + node.count].iter() {
+                    let limit = result.as_ref().map_or(f64::INFINITY, |(d, _)| *d);
+                    if self.bounds[index]
+                        .entry_distance(origin, direction, limit)
+                        .is_some()
+                    {
+                        consider(index, &mut result);
+                    }
+                }
+                continue;
+            }
+
+            // visit the nearer child first so the farther one is more likely to be culled
+            let (near, far) = match (
+                self.nodes[node.start]
+                    .bounds
+                    .entry_distance(origin, direction, limit),
+                self.nodes[node.start + 1]
+                    .bounds
+                    .entry_distance(origin, direction, limit),
+            ) {
+                (Some(a), Some(b)) if b < a => (Some(node.start + 1), Some(node.start)),
+                (a, b) => (a.map(|_| node.start), b.map(|_| node.start + 1)),
+            };
+            stack.extend(far);
+            stack.extend(near);
+        }
+
+        result
+    }
+
+    /// Returns whether `test` returns true for any item whose bounds the ray
+    /// enters before `max_distance`.
+    pub fn any(
+        &self,
+        origin: Position,
+        direction: Direction,
+        max_distance: f64,
+        mut test: impl FnMut(usize) -> bool,
+    ) -> bool {
+        if self.unbounded.iter().any(|&index| test(index)) {
+            return true;
+        }
+        if self.nodes.is_empty() {
+            return false;
+        }
+
+        let mut stack = vec![0];
+        while let Some(node) = stack.pop() {
+            let node = &self.nodes[node];
+            if node
+                .bounds
+                .entry_distance(origin, direction, max_distance)
+                .is_none()
+            {
+                continue;
+            }
+            if node.count > 0 {
+                if self.items[node.start..node.start + node.count]
+                    .iter()
+                    .any(|&index| {
+                        self.bounds[index]
+                            .entry_distance(origin, direction, max_distance)
+                            .is_some()
+                            && test(index)
+                    })
+                {
+                    return true;
+                }
+            } else {
+                stack.push(node.start);
+                stack.push(node.start + 1);
+            }
+        }
+
+        false
+    }
+
+    /// Calls `visit` for every item whose bounds the ray enters before
+    /// `max_distance`, in no particular order.
+    pub fn all(
+        &self,
+        origin: Position,
+        direction: Direction,
+        max_distance: f64,
+        mut visit: impl FnMut(usize),
+    ) {
+        self.any(origin, direction, max_distance, |index| {
+            visit(index);
+            false
+        });
+    }
+}
+
+const EMPTY: Aabb = Aabb {
+    min: Vec3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+    max: Vec3::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit_box(x: f64, y: f64, z: f64) -> Aabb {
+        Aabb::new(Vec3::new(x, y, z), Vec3::new(x + 1.0, y + 1.0, z + 1.0))
+    }
+
+    #[test]
+    fn closest_matches_linear_search() {
+        let bounds: Vec<Option<Aabb>> = (0..50)
+            .map(|i| match i % 7 {
+                0 => None,
+                _ => Some(unit_box(i as f64 * 2.0, (i % 3) as f64, (i % 5) as f64)),
+            })
+            .collect();
+        let bvh = Bvh::new(&bounds);
+        let origin = Position::new(Vec3::new(-10.0, 0.5, 0.5));
+
+        for target in 0..50 {
+            let goal = unit_box(
+                target as f64 * 2.0,
+                (target % 3) as f64,
+                (target % 5) as f64,
+            )
+            .center();
+            let direction = Direction::new(goal - *origin);
+            let distance = |index: usize| match bounds[index] {
+                Some(b) => b.entry_distance(origin, direction, f64::INFINITY),
+                None => Some(1000.0),
+            };
+
+            let expected = (0..bounds.len())
+                .filter_map(|i| distance(i).map(|d| (d, i)))
+                .min_by(|a, b| a.0.total_cmp(&b.0));
+            let actual = bvh.closest(origin, direction, |i, _| distance(i).map(|d| (d, i)));
+            assert_eq!(expected.map(|e| e.1), actual.map(|a| a.1));
+
+            assert!(!bvh.any(origin, direction, f64::INFINITY, |_| false));
+        }
+    }
+
+    #[test]
+    fn any_and_all_prune_by_distance_and_direction() {
+        // along the ray at 0.5, 0.5: near at 5, far at 20, and off the ray
+        let bounds = [
+            Some(unit_box(5.0, 0.0, 0.0)),
+            Some(unit_box(20.0, 0.0, 0.0)),
+            Some(unit_box(5.0, 10.0, 0.0)),
+            Some(unit_box(20.0, 0.0, 10.0)),
+        ];
+        let bvh = Bvh::new(&bounds);
+        let origin = Position::new(Vec3::new(0.0, 0.5, 0.5));
+        let direction = Direction::new(Vec3::X);
+
+        assert!(bvh.any(origin, direction, 10.0, |i| i == 0));
+        assert!(!bvh.any(origin, direction, 10.0, |i| i == 1));
+        assert!(bvh.any(origin, direction, 30.0, |i| i == 1));
+
+        let mut visited = Vec::new();
+        bvh.all(origin, direction, f64::INFINITY, |i| visited.push(i));
+        visited.sort();
+        assert_eq!(visited, [0, 1]);
+
+        visited.clear();
+        bvh.all(origin, direction, 10.0, |i| visited.push(i));
+        assert_eq!(visited, [0]);
+    }
+}
@@ -1,51 +1,215 @@
 use crate::types::{
-    math::Direction,
-    rt::{Ray, Scene},
+    math::{Direction, Position},
+    rt::{Hit, Integrator, Ray, RayCone, Scene},
 };
 use ::types::{HDRColor, LDRColor};
+use random::Rng;
 
+pub mod bvh;
+pub mod path;
+pub mod random;
+pub mod tonemap;
 pub mod types;
 
-pub fn sample(scene: &Scene, x: f64, y: f64) -> HDRColor {
+#[cfg(test)]
+mod test;
+
+pub fn sample(scene: &Scene, x: f64, y: f64, rng: &mut Rng) -> HDRColor {
     let ray = scene.camera.ray(x, y);
-    if let Some(hit) = scene.test(ray) {
-        let position = ray.origin + ray.direction * hit.distance + hit.normal * 1e-3;
-        let mut result = scene.ambient_light * hit.albedo;
-        for light in scene.lights.iter() {
-            if let Some((color, direction, distance)) = light.test(position) {
-                let shadow_ray = Ray {
-                    origin: position,
+    match scene.integrator {
+        Integrator::Whitted => trace(scene, ray, scene.max_depth, rng),
+        Integrator::Path => path::trace(scene, ray, rng),
+    }
+}
+
+fn trace(scene: &Scene, ray: Ray, depth: usize, rng: &mut Rng) -> HDRColor {
+    let Some(hit) = scene.test(ray) else {
+        return (scene.sky_color)(ray.direction);
+    };
+
+    // implicit surfaces don't orient their normals, so face them toward the viewer
+    let normal = if hit.normal.dot(ray.direction) > 0.0 {
+        -hit.normal
+    } else {
+        hit.normal
+    };
+    let point = ray.origin + ray.direction * hit.distance;
+    let position = point + normal * 1e-3;
+    let opacity = 1.0 - hit.transmission;
+    let mut result = emission(&hit)
+        + scene.ambient_light * hit.albedo * opacity
+        + direct_lighting(
+            scene,
+            &hit,
+            normal,
+            position,
+            -ray.direction,
+            scene.light_samples,
+            rng,
+        ) * opacity;
+
+    if depth == 0 {
+        return result;
+    }
+
+    let cos_theta = normal.dot(-ray.direction);
+    let reflected = Ray {
+        origin: position,
+        direction: reflect(ray.direction, normal),
+        cone: ray.cone.at(hit.distance),
+    };
+
+    let f0 = base_reflectivity(hit.albedo, hit.metallic);
+    // rough surfaces scatter most of the mirrored light away
+    let gloss = (1.0 - hit.roughness) * opacity;
+    let weight = LDRColor {
+        r: fresnel_schlick(cos_theta, f0.r) * gloss,
+        g: fresnel_schlick(cos_theta, f0.g) * gloss,
+        b: fresnel_schlick(cos_theta, f0.b) * gloss,
+    };
+    if weight.r.max(weight.g).max(weight.b) > 1e-3 {
+        result = result + weight * trace(scene, reflected, depth - 1, rng);
+    }
+
+    if hit.transmission > 0.0 {
+        let eta = if hit.is_front_face {
+            1.0 / hit.ior
+        } else {
+            hit.ior
+        };
+        let reflectance = match refract(ray.direction, normal, eta) {
+            Some(direction) => {
+                let reflectance = fresnel_dielectric(cos_theta, eta);
+                let refracted = Ray {
+                    origin: point + normal * -1e-3,
                     direction,
+                    cone: ray.cone.at(hit.distance),
                 };
+                let transmitted = trace(scene, refracted, depth - 1, rng);
+                result =
+                    result + hit.albedo * transmitted * ((1.0 - reflectance) * hit.transmission);
+                reflectance
+            }
+            // total internal reflection
+            None => 1.0,
+        };
+        result =
+            result + trace(scene, reflected, depth - 1, rng) * (reflectance * hit.transmission);
+    }
 
-                let shadow_hit = scene.test(shadow_ray);
+    result
+}
 
-                let is_shadowed = if distance.is_finite() {
-                    shadow_hit.map(|x| x.distance).unwrap_or(f64::INFINITY) < distance
-                } else {
-                    shadow_hit.is_some()
-                };
+/// Radiance the surface emits toward the viewer.
+pub(crate) fn emission(hit: &Hit) -> HDRColor {
+    if hit.is_front_face {
+        hit.emission
+    } else {
+        HDRColor::BLACK
+    }
+}
+
+/// Light arriving directly from the scene's lights, using about `samples`
+/// shadow rays for each area light.
+pub(crate) fn direct_lighting(
+    scene: &Scene,
+    hit: &Hit,
+    normal: Direction,
+    position: Position,
+    surface_to_view: Direction,
+    samples: usize,
+    rng: &mut Rng,
+) -> HDRColor {
+    let mut result = HDRColor::BLACK;
+    for light in scene.lights.iter() {
+        for (color, direction, distance) in light.sample(position, samples, rng) {
+            let shadow_ray = Ray {
+                origin: position,
+                direction,
+                cone: RayCone::default(),
+            };
 
-                if !is_shadowed {
-                    result = result
-                        + brdf(
-                            -ray.direction,
-                            direction,
-                            hit.normal,
-                            hit.roughness,
-                            hit.metallic,
-                            hit.albedo,
-                            color,
-                        )
-                }
+            if !scene.occluded(shadow_ray, distance) {
+                result = result
+                    + brdf(
+                        surface_to_view,
+                        direction,
+                        normal,
+                        hit.roughness,
+                        hit.metallic,
+                        hit.albedo,
+                        color,
+                    )
             }
         }
-        result
-    } else {
-        (scene.sky_color)(ray.direction)
+    }
+    result
+}
+
+pub(crate) fn reflect(direction: Direction, normal: Direction) -> Direction {
+    Direction::new(*direction - *normal * (2.0 * direction.dot(normal)))
+}
+
+pub(crate) fn refract(direction: Direction, normal: Direction, eta: f64) -> Option<Direction> {
+    let cos_i = normal.dot(-direction);
+    let sin2_t = eta * eta * (1.0 - cos_i * cos_i);
+    if sin2_t > 1.0 {
+        return None;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    Some(Direction::new(
+        *direction * eta + *normal * (eta * cos_i - cos_t),
+    ))
+}
+
+pub(crate) fn fresnel_dielectric(cos_i: f64, eta: f64) -> f64 {
+    let cos_i = cos_i.clamp(0.0, 1.0);
+    let sin2_t = eta * eta * (1.0 - cos_i * cos_i);
+    if sin2_t > 1.0 {
+        return 1.0;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    let r_s = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    let r_p = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    (r_s * r_s + r_p * r_p) / 2.0
+}
+
+pub(crate) fn fresnel_schlick(cos_theta: f64, f0: f64) -> f64 {
+    let cos_theta = cos_theta.clamp(0.0, 1.0);
+    f0 + (1.0 - f0) * (1.0 - cos_theta).powf(5.0)
+}
+
+pub(crate) fn base_reflectivity(albedo: LDRColor, metallic: f64) -> LDRColor {
+    LDRColor {
+        r: albedo.r * metallic + (1.0 - metallic) * 0.04,
+        g: albedo.g * metallic + (1.0 - metallic) * 0.04,
+        b: albedo.b * metallic + (1.0 - metallic) * 0.04,
     }
 }
 
+fn ggx_ndf(n: Direction, h: Direction, roughness: f64) -> f64 {
+    let alpha = roughness * roughness;
+    let alpha2 = alpha * alpha;
+    let cos_n_h = n.dot(h).clamp(0.0, 1.0);
+    let cos_n_h2 = cos_n_h * cos_n_h;
+    let denom = cos_n_h2 * alpha2 + (1.0 - cos_n_h2);
+    alpha2 / (std::f64::consts::PI * denom * denom)
+}
+
+pub(crate) fn geometric_attenuation(
+    n: Direction,
+    v: Direction,
+    l: Direction,
+    roughness: f64,
+) -> f64 {
+    let k = (roughness + 1.0) * (roughness + 1.0) / 8.0;
+    let cos_n_v = n.dot(v).max(1e-5);
+    let g_v = cos_n_v / (cos_n_v * (1.0 - k) + k);
+    let cos_n_l = n.dot(l).max(1e-5);
+    let g_l = cos_n_l / (cos_n_l * (1.0 - k) + k);
+    g_v * g_l
+}
+
 fn brdf(
     surface_to_view: Direction,
     surface_to_light: Direction,
@@ -55,36 +219,9 @@ fn brdf(
     albedo: LDRColor,
     light_color: HDRColor,
 ) -> HDRColor {
-    fn fresnel_schlick(cos_theta: f64, f0: f64) -> f64 {
-        let cos_theta = cos_theta.clamp(0.0, 1.0);
-        f0 + (1.0 - f0) * (1.0 - cos_theta).powf(5.0)
-    }
-
-    fn ggx_ndf(n: Direction, h: Direction, roughness: f64) -> f64 {
-        let alpha = roughness * roughness;
-        let alpha2 = alpha * alpha;
-        let cos_n_h = n.dot(h).clamp(0.0, 1.0);
-        let cos_n_h2 = cos_n_h * cos_n_h;
-        let denom = cos_n_h2 * alpha2 + (1.0 - cos_n_h2);
-        alpha2 / (std::f64::consts::PI * denom * denom)
-    }
-
-    fn geometric_attenuation(n: Direction, v: Direction, l: Direction, roughness: f64) -> f64 {
-        let k = (roughness + 1.0) * (roughness + 1.0) / 8.0;
-        let cos_n_v = n.dot(v).max(1e-5);
-        let g_v = cos_n_v / (cos_n_v * (1.0 - k) + k);
-        let cos_n_l = n.dot(l).max(1e-5);
-        let g_l = cos_n_l / (cos_n_l * (1.0 - k) + k);
-        g_v * g_l
-    }
-
     let n_dot_l = surface_normal.dot(surface_to_light).max(0.0);
 
-    let f0 = LDRColor {
-        r: albedo.r * metallic + (1.0 - metallic) * 0.04,
-        g: albedo.g * metallic + (1.0 - metallic) * 0.04,
-        b: albedo.b * metallic + (1.0 - metallic) * 0.04,
-    };
+    let f0 = base_reflectivity(albedo, metallic);
 
     let h = Direction::new(*surface_to_view + *surface_to_light);
     let d = ggx_ndf(surface_normal, h, roughness);
@@ -122,3 +259,64 @@ fn brdf(
         b: (diffuse.b + specular.b) * light_color.b * n_dot_l,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test::{scene, Sphere},
+        types::math::Vec3,
+    };
+
+    #[test]
+    fn camera_rays_see_emission() {
+        let glowing = Sphere {
+            emission: HDRColor {
+                r: 2.0,
+                g: 3.0,
+                b: 4.0,
+            },
+            ..Sphere::new(Vec3::ZERO, 1.0)
+        };
+        let mut scene = scene(vec![Box::new(glowing)], Vec::new(), 0.0, 4);
+        let mut rng = Rng::new(1);
+        for integrator in [Integrator::Whitted, Integrator::Path] {
+            scene.integrator = integrator;
+            let color = sample(&scene, 0.3, 0.2, &mut rng);
+            assert_eq!(
+                (color.r, color.g, color.b),
+                (2.0, 3.0, 4.0),
+                "{:?}",
+                integrator
+            );
+        }
+    }
+
+    #[test]
+    fn paths_find_emitters_without_a_light() {
+        // the emitter above has no emitter() light, only bounces can reach it
+        let glowing = Sphere {
+            emission: HDRColor {
+                r: 10.0,
+                g: 10.0,
+                b: 10.0,
+            },
+            ..Sphere::new(Vec3::new(0.0, 3.0, 0.0), 1.0)
+        };
+        let white = Sphere::new(Vec3::ZERO, 1.0);
+        let mut scene = scene(vec![Box::new(white), Box::new(glowing)], Vec::new(), 0.0, 4);
+        assert!(scene.lights.is_empty());
+        let mut rng = Rng::new(1);
+
+        scene.integrator = Integrator::Whitted;
+        assert_eq!(sample(&scene, 0.0, 0.9, &mut rng).r, 0.0);
+
+        scene.integrator = Integrator::Path;
+        let count = 2000;
+        let mean = (0..count)
+            .map(|_| sample(&scene, 0.0, 0.9, &mut rng).r)
+            .sum::<f64>()
+            / count as f64;
+        assert!(mean > 0.1, "{}", mean);
+    }
+}
@@ -0,0 +1,280 @@
+use std::f64::consts::PI;
+
+use crate::{
+    base_reflectivity, direct_lighting, emission, fresnel_dielectric, fresnel_schlick,
+    geometric_attenuation,
+    random::Rng,
+    reflect, refract,
+    types::{
+        math::Direction,
+        rt::{Hit, Ray, Scene},
+    },
+};
+use ::types::{HDRColor, LDRColor};
+
+/// Bounces after which Russian roulette starts terminating dim paths.
+const ROULETTE_DEPTH: usize = 3;
+
+struct Bounce {
+    direction: Direction,
+    weight: HDRColor,
+    is_transmitted: bool,
+    /// Whether the direction is a mirror or refraction one, which light
+    /// sampling can't find.
+    is_specular: bool,
+}
+
+/// Unidirectional path tracer with next-event estimation against `scene.lights`.
+///
+/// The constant `ambient_light` is not used here; indirect light comes from the
+/// bounces themselves.
+pub fn trace(scene: &Scene, ray: Ray, rng: &mut Rng) -> HDRColor {
+    let mut result = HDRColor::BLACK;
+    let mut throughput = HDRColor::default();
+    let mut ray = ray;
+    // camera rays and specular bounces see sampled emitters directly
+    let mut is_specular = true;
+
+    for depth in 0..=scene.max_depth {
+        let Some((index, hit)) = scene.test_object(ray) else {
+            result = result + throughput * (scene.sky_color)(ray.direction);
+            break;
+        };
+
+        if is_specular || !scene.sampled_emission[index] {
+            result = result + throughput * emission(&hit);
+        }
+
+        let normal = if hit.normal.dot(ray.direction) > 0.0 {
+            -hit.normal
+        } else {
+            hit.normal
+        };
+        let point = ray.origin + ray.direction * hit.distance;
+        let position = point + normal * 1e-3;
+        let opacity = 1.0 - hit.transmission;
+
+        if opacity > 0.0 {
+            // one shadow ray per light is enough, the pixel averages many paths anyway
+            let direct = direct_lighting(scene, &hit, normal, position, -ray.direction, 1, rng);
+            result = result + throughput * direct * opacity;
+        }
+
+        let Some(bounce) = scatter(&hit, ray.direction, normal, rng) else {
+            break;
+        };
+        throughput = throughput * bounce.weight;
+        is_specular = bounce.is_specular;
+        ray = Ray {
+            origin: if bounce.is_transmitted {
+                point + normal * -1e-3
+            } else {
+                position
+            },
+            direction: bounce.direction,
+            cone: ray.cone.at(hit.distance),
+        };
+
+        if depth >= ROULETTE_DEPTH {
+            let survival = throughput
+                .r
+                .max(throughput.g)
+                .max(throughput.b)
+                .clamp(0.05, 0.95);
+            if rng.next_f64() >= survival {
+                break;
+            }
+            throughput = throughput / survival;
+        }
+    }
+
+    result
+}
+
+/// Picks the next path direction and its throughput weight (BSDF * cos / pdf).
+fn scatter(hit: &Hit, direction: Direction, normal: Direction, rng: &mut Rng) -> Option<Bounce> {
+    let view = -direction;
+
+    // transmission is chosen with its own probability, so neither lobe is rescaled by it
+    if hit.transmission > 0.0 && rng.next_f64() < hit.transmission {
+        let eta = if hit.is_front_face {
+            1.0 / hit.ior
+        } else {
+            hit.ior
+        };
+        let reflectance = fresnel_dielectric(normal.dot(view), eta);
+        if let Some(refracted) = refract(direction, normal, eta) {
+            if rng.next_f64() >= reflectance {
+                return Some(Bounce {
+                    direction: refracted,
+                    weight: HDRColor::default() * hit.albedo,
+                    is_transmitted: true,
+                    is_specular: true,
+                });
+            }
+        }
+        return Some(Bounce {
+            direction: reflect(direction, normal),
+            weight: HDRColor::default(),
+            is_transmitted: false,
+            is_specular: true,
+        });
+    }
+
+    let cos_n_v = normal.dot(view).max(1e-5);
+    let f0 = base_reflectivity(hit.albedo, hit.metallic);
+    let fresnel = LDRColor {
+        r: fresnel_schlick(cos_n_v, f0.r),
+        g: fresnel_schlick(cos_n_v, f0.g),
+        b: fresnel_schlick(cos_n_v, f0.b),
+    };
+    let diffuse = LDRColor {
+        r: (1.0 - fresnel.r) * (1.0 - hit.metallic) * hit.albedo.r,
+        g: (1.0 - fresnel.g) * (1.0 - hit.metallic) * hit.albedo.g,
+        b: (1.0 - fresnel.b) * (1.0 - hit.metallic) * hit.albedo.b,
+    };
+    let specular_estimate = (fresnel.r + fresnel.g + fresnel.b) / 3.0;
+    let diffuse_estimate = (diffuse.r + diffuse.g + diffuse.b) / 3.0;
+    if specular_estimate + diffuse_estimate <= 0.0 {
+        return None;
+    }
+    let specular_probability = specular_estimate / (specular_estimate + diffuse_estimate);
+
+    if rng.next_f64() < specular_probability {
+        let h = sample_ggx(normal, hit.roughness, rng);
+        let light = reflect(direction, h);
+        let cos_n_l = normal.dot(light);
+        if cos_n_l <= 0.0 {
+            return None;
+        }
+        let cos_v_h = view.dot(h).max(1e-5);
+        let cos_n_h = normal.dot(h).max(1e-5);
+        let g = geometric_attenuation(normal, view, light, hit.roughness);
+        let factor = g * cos_v_h / (cos_n_v * cos_n_h * specular_probability);
+        Some(Bounce {
+            direction: light,
+            weight: HDRColor {
+                r: fresnel_schlick(cos_v_h, f0.r) * factor,
+                g: fresnel_schlick(cos_v_h, f0.g) * factor,
+                b: fresnel_schlick(cos_v_h, f0.b) * factor,
+            },
+            is_transmitted: false,
+            // a perfect mirror lobe gets nothing from light sampling
+            is_specular: hit.roughness == 0.0,
+        })
+    } else {
+        Some(Bounce {
+            direction: sample_cosine(normal, rng),
+            weight: HDRColor::default() * diffuse / (1.0 - specular_probability),
+            is_transmitted: false,
+            is_specular: false,
+        })
+    }
+}
+
+fn sample_cosine(normal: Direction, rng: &mut Rng) -> Direction {
+    let (tangent, bitangent) = normal.tangent_frame();
+    let r = rng.next_f64().sqrt();
+    let phi = 2.0 * PI * rng.next_f64();
+    let z = (1.0 - r * r).max(0.0).sqrt();
+    Direction::new(tangent * (r * phi.cos()) + bitangent * (r * phi.sin()) + *normal * z)
+}
+
+/// Samples a GGX-distributed microfacet normal around `normal`.
+fn sample_ggx(normal: Direction, roughness: f64, rng: &mut Rng) -> Direction {
+    let alpha = roughness * roughness;
+    let u = rng.next_f64();
+    let phi = 2.0 * PI * rng.next_f64();
+    let tan2_theta = alpha * alpha * u / (1.0 - u).max(1e-12);
+    let cos_theta = 1.0 / (1.0 + tan2_theta).sqrt();
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    let (tangent, bitangent) = normal.tangent_frame();
+    Direction::new(
+        tangent * (sin_theta * phi.cos())
+            + bitangent * (sin_theta * phi.sin())
+            + *normal * cos_theta,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::Ordering;
+
+    use super::*;
+    use crate::{
+        test::{ray, scene, PointLight, Sphere},
+        types::math::{Position, Vec3},
+    };
+
+    #[test]
+    fn white_sphere_under_a_constant_sky_disappears() {
+        let scene = scene(
+            vec![Box::new(Sphere::new(Vec3::ZERO, 1.0))],
+            Vec::new(),
+            0.5,
+            8,
+        );
+        let mut rng = Rng::new(1);
+        let origin = Vec3::new(0.0, 0.0, -5.0);
+        for target in [Vec3::ZERO, Vec3::new(0.5, 0.3, 0.0)] {
+            let count = 20000;
+            let mut sum = 0.0;
+            for _ in 0..count {
+                sum += trace(&scene, ray(origin, target - origin), &mut rng).g;
+            }
+            // the microfacet lobe loses a few percent to shadowing-masking
+            let mean = sum / count as f64;
+            assert!((mean / 0.5 - 1.0).abs() < 0.05, "{}", mean);
+        }
+    }
+
+    #[test]
+    fn russian_roulette_ends_paths() {
+        // inside a closed white sphere, paths never escape
+        let sphere = Sphere::new(Vec3::ZERO, 1.0);
+        let tests = sphere.tests.clone();
+        let scene = scene(vec![Box::new(sphere)], Vec::new(), 1.0, usize::MAX);
+        let mut rng = Rng::new(1);
+        let count = 1000;
+        for _ in 0..count {
+            let color = trace(&scene, ray(Vec3::ZERO, Vec3::X), &mut rng);
+            assert_eq!(color.r, 0.0);
+        }
+        // without roulette every path would run to max_depth
+        let bounces = tests.load(Ordering::Relaxed) as f64 / count as f64;
+        assert!(bounces < 30.0, "{} bounces per path", bounces);
+    }
+
+    #[test]
+    fn light_sampling_matches_whitted_direct_lighting() {
+        let scene = scene(
+            vec![Box::new(Sphere::new(Vec3::ZERO, 1.0))],
+            vec![Box::new(PointLight(Position::new(Vec3::new(
+                0.0, 5.0, -5.0,
+            ))))],
+            0.0,
+            4,
+        );
+        let mut rng = Rng::new(1);
+        let origin = Vec3::new(0.0, 0.0, -5.0);
+        for target in [
+            Vec3::ZERO,
+            Vec3::new(0.5, 0.3, 0.0),
+            Vec3::new(-0.2, 0.6, 0.0),
+        ] {
+            let ray = ray(origin, target - origin);
+            let direct = crate::trace(&scene, ray, 0, &mut rng);
+            assert!(direct.r > 0.0);
+            // bounces off the lone convex sphere only see the black sky
+            for _ in 0..100 {
+                let color = trace(&scene, ray, &mut rng);
+                assert!(
+                    (color.r - direct.r).abs() < 1e-12,
+                    "{:?} {:?}",
+                    color,
+                    direct
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,46 @@
+/// Small xorshift64* generator; seeded per pixel so renders stay reproducible.
+#[derive(Clone, Debug)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        // splitmix64 scrambles nearby seeds (e.g. neighbouring pixels) apart
+        let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        Rng {
+            state: if z == 0 { 0x9e37_79b9_7f4a_7c15 } else { z },
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// Uniform sample in `[0, 1)`.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 * (1.0 / (1u64 << 53) as f64)
+    }
+
+    /// Jittered points in `[0, 1)²`, one in each cell of a k x k grid where k
+    /// is the square root of `count` rounded to the nearest integer (at least 1).
+    pub fn stratified_2d(&mut self, count: usize) -> Vec<(f64, f64)> {
+        let k = ((count as f64).sqrt().round() as usize).max(1);
+        let mut result = Vec::with_capacity(k * k);
+        for y in 0..k {
+            for x in 0..k {
+                result.push((
+                    (x as f64 + self.next_f64()) / k as f64,
+                    (y as f64 + self.next_f64()) / k as f64,
+                ));
+            }
+        }
+        result
+    }
+}
@@ -0,0 +1,131 @@
+//! Objects and scenes for the integrator tests.
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use crate::{
+    bvh::Bvh,
+    tonemap::ToneMapper,
+    types::{
+        math::{Aabb, Direction, Position, Vec3},
+        rt::{Camera, Hit, Integrator, Light, RTObject, Ray, RayCone, Scene},
+    },
+};
+use ::types::{HDRColor, LDRColor};
+
+/// Diffuse white sphere, counting how often rays are tested against it.
+pub struct Sphere {
+    pub center: Vec3,
+    pub radius: f64,
+    pub emission: HDRColor,
+    pub tests: Arc<AtomicUsize>,
+}
+
+impl Sphere {
+    pub fn new(center: Vec3, radius: f64) -> Sphere {
+        Sphere {
+            center,
+            radius,
+            emission: HDRColor::BLACK,
+            tests: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    fn hit(&self, distance: f64, normal: Vec3, is_front_face: bool) -> Hit {
+        Hit {
+            is_front_face,
+            albedo: LDRColor::new(1.0, 1.0, 1.0),
+            normal: Direction::new(normal),
+            distance,
+            roughness: 1.0,
+            metallic: 0.0,
+            transmission: 0.0,
+            ior: 1.5,
+            emission: self.emission,
+        }
+    }
+}
+
+impl RTObject for Sphere {
+    fn test(&self, ray: Ray) -> Vec<Hit> {
+        self.tests.fetch_add(1, Ordering::Relaxed);
+        let o = *ray.origin - self.center;
+        let d = *ray.direction;
+        let b = o.dot(d);
+        let discriminant = b * b - (o.dot(o) - self.radius * self.radius);
+        if discriminant < 0.0 {
+            return Vec::new();
+        }
+        let (t1, t2) = (-b - discriminant.sqrt(), -b + discriminant.sqrt());
+        let normal = |t: f64| o + d * t;
+        match (t1 >= 0.0, t2 >= 0.0) {
+            (true, _) => vec![
+                self.hit(t1, normal(t1), true),
+                self.hit(t2, normal(t2), false),
+            ],
+            (false, true) => vec![self.hit(0.0, -d, true), self.hit(t2, normal(t2), false)],
+            _ => Vec::new(),
+        }
+    }
+
+    fn aabb(&self) -> Option<Aabb> {
+        let extent = Vec3::new(self.radius, self.radius, self.radius);
+        Some(Aabb::new(self.center - extent, self.center + extent))
+    }
+}
+
+pub struct PointLight(pub Position);
+
+impl Light for PointLight {
+    fn test(&self, position: Position) -> Option<(HDRColor, Direction, f64)> {
+        let (direction, distance) = (self.0 - position).direction_and_length();
+        Some((HDRColor::new(10.0, 10.0, 10.0), direction, distance))
+    }
+}
+
+/// Looks from (0, 0, -5) through the point (x, y, 0).
+struct Pinhole;
+
+impl Camera for Pinhole {
+    fn ray(&self, x: f64, y: f64) -> Ray {
+        let origin = Vec3::new(0.0, 0.0, -5.0);
+        ray(origin, Vec3::new(x, y, 0.0) - origin)
+    }
+}
+
+/// Path traced scene under a constant `sky`; no object's emission is sampled.
+pub fn scene(
+    objects: Vec<Box<dyn RTObject + Send + Sync>>,
+    lights: Vec<Box<dyn Light + Send + Sync>>,
+    sky: f64,
+    max_depth: usize,
+) -> Scene {
+    let mut scene = Scene {
+        image_width: 1,
+        image_height: 1,
+        camera: Box::new(Pinhole),
+        sampled_emission: vec![false; objects.len()],
+        objects,
+        lights,
+        sky_color: Arc::new(move |_| HDRColor::new(sky, sky, sky)),
+        ambient_light: HDRColor::BLACK,
+        max_depth,
+        integrator: Integrator::Path,
+        samples_per_pixel: 1,
+        light_samples: 1,
+        tone_mapper: ToneMapper::default(),
+        bvh: Bvh::new(&[]),
+    };
+    scene.build_bvh();
+    scene
+}
+
+pub fn ray(origin: Vec3, direction: Vec3) -> Ray {
+    Ray {
+        origin: Position::new(origin),
+        direction: Direction::new(direction),
+        cone: RayCone::default(),
+    }
+}
@@ -0,0 +1,80 @@
+use ::types::{HDRColor, LDRColor};
+
+/// Operator compressing scene radiance into the displayable range.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ToneMapping {
+    /// `1 - e^-x`
+    Exponential,
+    /// `x / (1 + x)`
+    Reinhard,
+    /// Reinhard that maps the white point to 1 instead of infinity.
+    ExtendedReinhard,
+    /// Narkowicz's fit of the ACES filmic curve.
+    Aces,
+    /// No compression, values above 1 are clipped.
+    Clamp,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ToneMapper {
+    pub tone_mapping: ToneMapping,
+    /// Multiplier applied before tone mapping.
+    pub exposure: f64,
+    /// Channel value mapped to 1 by [`ToneMapping::ExtendedReinhard`].
+    pub white_point: f64,
+    /// Display gamma, unused when `srgb` is set.
+    pub gamma: f64,
+    /// Encode with the sRGB transfer function instead of a pure power curve.
+    pub srgb: bool,
+}
+
+impl Default for ToneMapper {
+    fn default() -> Self {
+        ToneMapper {
+            tone_mapping: ToneMapping::Exponential,
+            exposure: 1.0,
+            white_point: 4.0,
+            gamma: 2.2,
+            srgb: false,
+        }
+    }
+}
+
+impl ToneMapper {
+    pub fn map(&self, color: HDRColor) -> LDRColor {
+        LDRColor {
+            r: self.map_channel(color.r),
+            g: self.map_channel(color.g),
+            b: self.map_channel(color.b),
+        }
+    }
+
+    fn map_channel(&self, value: f64) -> f64 {
+        let x = (value * self.exposure).max(0.0);
+        let mapped = match self.tone_mapping {
+            ToneMapping::Exponential => 1.0 - (-x).exp(),
+            ToneMapping::Reinhard => x / (1.0 + x),
+            ToneMapping::ExtendedReinhard => {
+                x * (1.0 + x / (self.white_point * self.white_point)) / (1.0 + x)
+            }
+            ToneMapping::Aces => (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14),
+            ToneMapping::Clamp => x,
+        }
+        .clamp(0.0, 1.0);
+
+        if self.srgb {
+            srgb_oetf(mapped)
+        } else {
+            mapped.powf(1.0 / self.gamma)
+        }
+    }
+}
+
+/// sRGB opto-electronic transfer function, linear [0, 1] to encoded [0, 1].
+pub fn srgb_oetf(linear: f64) -> f64 {
+    if linear <= 0.0031308 {
+        linear * 12.92
+    } else {
+        1.055 * linear.powf(1.0 / 2.4) - 0.055
+    }
+}
@@ -146,6 +146,14 @@ impl Direction {
     pub fn dot(self, rhs: Direction) -> f64 {
         (*self).dot(*rhs)
     }
+
+    /// Two unit vectors perpendicular to this direction and to each other.
+    pub fn tangent_frame(self) -> (Vec3, Vec3) {
+        let helper = if self.x.abs() > 0.9 { Vec3::Y } else { Vec3::X };
+        let tangent = self.cross(helper).normalize();
+        let bitangent = self.cross(tangent);
+        (tangent, bitangent)
+    }
 }
 
 impl From<Direction> for Vec3 {
@@ -247,3 +255,346 @@ impl From<Move> for Position {
         Position(val.0)
     }
 }
+
+/// Axis-aligned bounding box. An empty box has `min > max` on some axis.
+#[derive(Clone, Copy, Debug)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    pub fn new(min: Vec3, max: Vec3) -> Aabb {
+        Aabb { min, max }
+    }
+
+    pub fn union(self, other: Aabb) -> Aabb {
+        Aabb {
+            min: Vec3::new(
+                self.min.x.min(other.min.x),
+                self.min.y.min(other.min.y),
+                self.min.z.min(other.min.z),
+            ),
+            max: Vec3::new(
+                self.max.x.max(other.max.x),
+                self.max.y.max(other.max.y),
+                self.max.z.max(other.max.z),
+            ),
+        }
+    }
+
+    pub fn intersection(self, other: Aabb) -> Aabb {
+        Aabb {
+            min: Vec3::new(
+                self.min.x.max(other.min.x),
+                self.min.y.max(other.min.y),
+                self.min.z.max(other.min.z),
+            ),
+            max: Vec3::new(
+                self.max.x.min(other.max.x),
+                self.max.y.min(other.max.y),
+                self.max.z.min(other.max.z),
+            ),
+        }
+    }
+
+    pub fn center(self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    /// Distance at which the ray enters the box (0 if it starts inside), if it
+    /// does so before `max_distance`.
+    pub fn entry_distance(
+        &self,
+        origin: Position,
+        direction: Direction,
+        max_distance: f64,
+    ) -> Option<f64> {
+        let mut t_min = 0.0_f64;
+        let mut t_max = max_distance;
+        for (o, d, min, max) in [
+            (origin.x, direction.x, self.min.x, self.max.x),
+            (origin.y, direction.y, self.min.y, self.max.y),
+            (origin.z, direction.z, self.min.z, self.max.z),
+        ] {
+            let inverse = 1.0 / d;
+            let t1 = (min - o) * inverse;
+            let t2 = (max - o) * inverse;
+            // f64::min/max drop the NaN from 0 * inf for rays on a slab plane
+            t_min = t_min.max(t1.min(t2));
+            t_max = t_max.min(t1.max(t2));
+        }
+        if t_min <= t_max {
+            Some(t_min)
+        } else {
+            None
+        }
+    }
+}
+
+/// Row-major 4x4 matrix, applied to column vectors.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Matrix4(pub [[f64; 4]; 4]);
+
+impl Matrix4 {
+    pub const IDENTITY: Matrix4 = Matrix4([
+        [1.0, 0.0, 0.0, 0.0],
+        [0.0, 1.0, 0.0, 0.0],
+        [0.0, 0.0, 1.0, 0.0],
+        [0.0, 0.0, 0.0, 1.0],
+    ]);
+
+    pub fn translation(offset: Vec3) -> Matrix4 {
+        let mut m = Matrix4::IDENTITY;
+        m.0[0][3] = offset.x;
+        m.0[1][3] = offset.y;
+        m.0[2][3] = offset.z;
+        m
+    }
+
+    pub fn scale(factor: Vec3) -> Matrix4 {
+        let mut m = Matrix4::IDENTITY;
+        m.0[0][0] = factor.x;
+        m.0[1][1] = factor.y;
+        m.0[2][2] = factor.z;
+        m
+    }
+
+    /// Counterclockwise rotation by `angle` radians around `axis`, seen
+    /// with the axis pointing at the viewer.
+    pub fn rotation(axis: Direction, angle: f64) -> Matrix4 {
+        let (sin, cos) = angle.sin_cos();
+        let Vec3 { x, y, z } = *axis;
+        let t = 1.0 - cos;
+        Matrix4([
+            [
+                t * x * x + cos,
+                t * x * y - sin * z,
+                t * x * z + sin * y,
+                0.0,
+            ],
+            [
+                t * x * y + sin * z,
+                t * y * y + cos,
+                t * y * z - sin * x,
+                0.0,
+            ],
+            [
+                t * x * z - sin * y,
+                t * y * z + sin * x,
+                t * z * z + cos,
+                0.0,
+            ],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn transpose(self) -> Matrix4 {
+        Matrix4(std::array::from_fn(|i| {
+            std::array::from_fn(|j| self.0[j][i])
+        }))
+    }
+
+    /// Whether the bottom row is (0, 0, 0, 1).
+    pub fn is_affine(self) -> bool {
+        self.0[3] == [0.0, 0.0, 0.0, 1.0]
+    }
+
+    /// Inverse of an affine matrix, None if it is singular.
+    pub fn affine_inverse(self) -> Option<Matrix4> {
+        let m = self.0;
+        let cofactor = |r0: usize, r1: usize, c0: usize, c1: usize| {
+            m[r0][c0] * m[r1][c1] - m[r0][c1] * m[r1][c0]
+        };
+        // adjugate of the linear 3x3 part
+        let adjugate = [
+            [
+                cofactor(1, 2, 1, 2),
+                -cofactor(0, 2, 1, 2),
+                cofactor(0, 1, 1, 2),
+            ],
+            [
+                -cofactor(1, 2, 0, 2),
+                cofactor(0, 2, 0, 2),
+                -cofactor(0, 1, 0, 2),
+            ],
+            [
+                cofactor(1, 2, 0, 1),
+                -cofactor(0, 2, 0, 1),
+                cofactor(0, 1, 0, 1),
+            ],
+        ];
+        let determinant =
+            m[0][0] * adjugate[0][0] + m[0][1] * adjugate[1][0] + m[0][2] * adjugate[2][0];
+        if determinant.abs() < 1e-12 {
+            return None;
+        }
+
+        let mut inverse = Matrix4::IDENTITY;
+        for (row, adjugate_row) in inverse.0.iter_mut().zip(adjugate) {
+            let linear = adjugate_row.map(|value| value / determinant);
+            row[..3].copy_from_slice(&linear);
+            // undo the translation
+            row[3] = -(0..3).map(|j| linear[j] * m[j][3]).sum::<f64>();
+        }
+        Some(inverse)
+    }
+
+    pub fn transform_point(self, point: Vec3) -> Vec3 {
+        let m = self.0;
+        Vec3::new(
+            m[0][0] * point.x + m[0][1] * point.y + m[0][2] * point.z + m[0][3],
+            m[1][0] * point.x + m[1][1] * point.y + m[1][2] * point.z + m[1][3],
+            m[2][0] * point.x + m[2][1] * point.y + m[2][2] * point.z + m[2][3],
+        )
+    }
+
+    /// Like [`Matrix4::transform_point`], ignoring the translation.
+    pub fn transform_vector(self, vector: Vec3) -> Vec3 {
+        let m = self.0;
+        Vec3::new(
+            m[0][0] * vector.x + m[0][1] * vector.y + m[0][2] * vector.z,
+            m[1][0] * vector.x + m[1][1] * vector.y + m[1][2] * vector.z,
+            m[2][0] * vector.x + m[2][1] * vector.y + m[2][2] * vector.z,
+        )
+    }
+}
+
+impl Mul<Matrix4> for Matrix4 {
+    type Output = Matrix4;
+
+    fn mul(self, rhs: Matrix4) -> Matrix4 {
+        Matrix4(std::array::from_fn(|i| {
+            std::array::from_fn(|j| (0..4).map(|k| self.0[i][k] * rhs.0[k][j]).sum())
+        }))
+    }
+}
+
+/// Affine map from an object's space into its parent's, with its inverse.
+#[derive(Clone, Copy, Debug)]
+pub struct Transform {
+    matrix: Matrix4,
+    inverse: Matrix4,
+}
+
+impl Transform {
+    /// None if the matrix is not affine or not invertible.
+    pub fn new(matrix: Matrix4) -> Option<Transform> {
+        if !matrix.is_affine() {
+            return None;
+        }
+        Some(Transform {
+            matrix,
+            inverse: matrix.affine_inverse()?,
+        })
+    }
+
+    pub fn matrix(&self) -> Matrix4 {
+        self.matrix
+    }
+
+    pub fn inverse(&self) -> Transform {
+        Transform {
+            matrix: self.inverse,
+            inverse: self.matrix,
+        }
+    }
+
+    pub fn position(&self, position: Position) -> Position {
+        Position::new(self.matrix.transform_point(*position))
+    }
+
+    /// The vector is scaled along with the space, use [`Transform::normal`]
+    /// for surface normals.
+    pub fn vector(&self, vector: Vec3) -> Vec3 {
+        self.matrix.transform_vector(vector)
+    }
+
+    /// Normals stay perpendicular to the transformed surface through the
+    /// inverse transpose.
+    pub fn normal(&self, normal: Direction) -> Direction {
+        Direction::new(self.inverse.transpose().transform_vector(*normal))
+    }
+
+    /// The factor all lengths are scaled by, if the transform keeps angles.
+    pub fn uniform_scale(&self) -> Option<f64> {
+        let columns = [Vec3::X, Vec3::Y, Vec3::Z].map(|axis| self.vector(axis));
+        let scale = columns[0].length();
+        let tolerance = 1e-9 * scale * scale;
+        let keeps_angles = columns
+            .iter()
+            .all(|column| (column.length_square() - scale * scale).abs() <= tolerance)
+            && columns[0].dot(columns[1]).abs() <= tolerance
+            && columns[1].dot(columns[2]).abs() <= tolerance
+            && columns[2].dot(columns[0]).abs() <= tolerance;
+        keeps_angles.then_some(scale)
+    }
+
+    /// Bounds of the transformed box.
+    pub fn aabb(&self, aabb: Aabb) -> Aabb {
+        let corner = |i: usize| {
+            let corner = self.matrix.transform_point(Vec3::new(
+                if i & 1 == 0 { aabb.min.x } else { aabb.max.x },
+                if i & 2 == 0 { aabb.min.y } else { aabb.max.y },
+                if i & 4 == 0 { aabb.min.z } else { aabb.max.z },
+            ));
+            Aabb::new(corner, corner)
+        };
+        (1..8).fold(corner(0), |bounds, i| bounds.union(corner(i)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: Vec3, b: Vec3) {
+        assert!((a - b).length() < 1e-9, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn rotates_counterclockwise() {
+        let rotation = Matrix4::rotation(Direction::new(Vec3::Z), std::f64::consts::FRAC_PI_2);
+        assert_close(rotation.transform_vector(Vec3::X), Vec3::Y);
+        assert_close(rotation.transform_vector(Vec3::Y), -Vec3::X);
+    }
+
+    #[test]
+    fn inverts_affine_matrices() {
+        let matrix = Matrix4::translation(Vec3::new(1.0, -2.0, 3.0))
+            * Matrix4::rotation(Direction::new(Vec3::new(1.0, 1.0, 0.0)), 0.7)
+            * Matrix4::scale(Vec3::new(2.0, 0.5, -3.0));
+        let transform = Transform::new(matrix).unwrap();
+        let point = Position::new(Vec3::new(0.3, -0.4, 5.0));
+        assert_close(
+            *transform.inverse().position(transform.position(point)),
+            *point,
+        );
+        assert!(Transform::new(Matrix4::scale(Vec3::new(1.0, 0.0, 1.0))).is_none());
+    }
+
+    #[test]
+    fn keeps_normals_perpendicular() {
+        let transform = Transform::new(
+            Matrix4::rotation(Direction::new(Vec3::Z), 0.5)
+                * Matrix4::scale(Vec3::new(4.0, 1.0, 1.0)),
+        )
+        .unwrap();
+        // the plane x + y = 0
+        let normal = Direction::new(Vec3::new(1.0, 1.0, 0.0));
+        let tangent = transform.vector(Vec3::new(1.0, -1.0, 0.0));
+        assert!(transform.normal(normal).dot(Direction::new(tangent)).abs() < 1e-9);
+        assert_eq!(transform.uniform_scale(), None);
+    }
+
+    #[test]
+    fn bounds_rotated_boxes() {
+        let transform = Transform::new(Matrix4::rotation(
+            Direction::new(Vec3::Y),
+            std::f64::consts::FRAC_PI_4,
+        ))
+        .unwrap();
+        let bounds = transform.aabb(Aabb::new(-Vec3::X - Vec3::Z, Vec3::X + Vec3::Z));
+        assert_close(bounds.max, Vec3::new(2f64.sqrt(), 0.0, 2f64.sqrt()));
+    }
+}
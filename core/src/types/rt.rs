@@ -2,12 +2,39 @@ use std::sync::Arc;
 
 use types::{HDRColor, LDRColor};
 
-use super::math::{Direction, Position};
+use crate::{bvh::Bvh, random::Rng, tonemap::ToneMapper};
+
+use super::math::{Aabb, Direction, Position};
 
 #[derive(Clone, Copy, Debug)]
 pub struct Ray {
     pub origin: Position,
     pub direction: Direction,
+    /// Beam the ray stands for, used to filter textures.
+    pub cone: RayCone,
+}
+
+/// Width of the beam around a ray, growing linearly along it. The default
+/// is a thin ray without a footprint.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RayCone {
+    pub width: f64,
+    /// Growth of the width per unit of distance.
+    pub spread: f64,
+}
+
+impl RayCone {
+    pub fn width_at(self, distance: f64) -> f64 {
+        self.width + self.spread * distance
+    }
+
+    /// The cone of a ray continuing from `distance` along this one.
+    pub fn at(self, distance: f64) -> RayCone {
+        RayCone {
+            width: self.width_at(distance),
+            spread: self.spread,
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -18,46 +45,125 @@ pub struct Hit {
     pub distance: f64,
     pub roughness: f64,
     pub metallic: f64,
+    pub transmission: f64,
+    pub ior: f64,
+    /// Radiance the surface emits toward the outside of the solid.
+    pub emission: HDRColor,
+}
+
+impl Hit {
+    /// Whether this is a real surface crossing closer than `max_distance`.
+    ///
+    /// Hits at the origin or at infinity only tell that the ray starts inside a solid.
+    pub fn is_occluding(&self, max_distance: f64) -> bool {
+        self.distance > 0.0 && self.distance.is_finite() && self.distance < max_distance
+    }
 }
 
 pub trait RTObject {
     fn test(&self, ray: Ray) -> Vec<Hit>;
+
+    /// Whether the ray crosses the surface before `max_distance`.
+    fn occluded(&self, ray: Ray, max_distance: f64) -> bool {
+        self.test(ray)
+            .iter()
+            .any(|hit| hit.is_occluding(max_distance))
+    }
+
+    /// Bounds of the solid, or None if it is unbounded.
+    fn aabb(&self) -> Option<Aabb> {
+        None
+    }
+
+    /// Light sampling the emission of the object, if its emitting surface
+    /// can be sampled directly. Other emitters only light the scene when
+    /// paths happen to hit them.
+    fn emitter(&self) -> Option<Box<dyn Light + Send + Sync>> {
+        None
+    }
 }
 
 pub trait Light {
     fn test(&self, position: Position) -> Option<(HDRColor, Direction, f64)>;
+
+    /// Estimates the light arriving at `position` with about `count` samples
+    /// of (color, direction, distance). The colors are already weighted so
+    /// that their sum is the estimate; each needs its own shadow ray.
+    ///
+    /// Delta lights only have one direction to sample, so by default this is
+    /// just [`Light::test`].
+    fn sample(
+        &self,
+        position: Position,
+        _count: usize,
+        _rng: &mut Rng,
+    ) -> Vec<(HDRColor, Direction, f64)> {
+        self.test(position).into_iter().collect()
+    }
 }
 
 pub trait Camera {
     fn ray(&self, x: f64, y: f64) -> Ray;
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Integrator {
+    /// Direct lighting plus recursive mirror reflection and refraction.
+    Whitted,
+    /// Monte Carlo path tracing with global illumination.
+    Path,
+}
+
 pub struct Scene {
     pub image_width: usize,
     pub image_height: usize,
     pub camera: Box<dyn Camera + Send + Sync>,
     pub objects: Vec<Box<dyn RTObject + Send + Sync>>,
     pub lights: Vec<Box<dyn Light + Send + Sync>>,
+    /// Whether the emission of each of `objects` is also one of `lights`,
+    /// in which case paths must not pick it up again when they hit it.
+    pub sampled_emission: Vec<bool>,
     pub sky_color: Arc<dyn Fn(Direction) -> HDRColor + Send + Sync>,
     pub ambient_light: HDRColor,
+    pub max_depth: usize,
+    pub integrator: Integrator,
+    pub samples_per_pixel: usize,
+    /// Shadow rays per area light and shading point for the Whitted integrator.
+    pub light_samples: usize,
+    pub tone_mapper: ToneMapper,
+    /// Acceleration structure over `objects`, see [`Scene::build_bvh`].
+    pub bvh: Bvh,
 }
 
 impl Scene {
+    /// Rebuilds the acceleration structure, must be called after `objects` changes.
+    pub fn build_bvh(&mut self) {
+        let bounds: Vec<_> = self.objects.iter().map(|object| object.aabb()).collect();
+        self.bvh = Bvh::new(&bounds);
+    }
+
     pub fn test(&self, ray: Ray) -> Option<Hit> {
-        let mut result = None::<Hit>;
-        for object in self.objects.iter() {
-            result = match (result, object.test(ray)) {
-                (None, current) => current.first().cloned(),
-                (previous, vec) if vec.is_empty() => previous,
-                (Some(previous), current) => {
-                    if previous.distance < current.first().unwrap().distance {
-                        Some(previous)
-                    } else {
-                        Some(current.first().unwrap().clone())
-                    }
-                }
-            }
-        }
-        result
+        self.test_object(ray).map(|(_, hit)| hit)
+    }
+
+    /// Like [`Scene::test`], also returning the index of the object hit.
+    pub fn test_object(&self, ray: Ray) -> Option<(usize, Hit)> {
+        self.bvh
+            .closest(ray.origin, ray.direction, |index, _| {
+                self.objects[index]
+                    .test(ray)
+                    .into_iter()
+                    .find(|hit| hit.is_occluding(f64::INFINITY))
+                    .map(|hit| (hit.distance, (index, hit)))
+            })
+            .map(|(_, hit)| hit)
+    }
+
+    /// Whether anything blocks the ray before `max_distance`, stopping at the first blocker.
+    pub fn occluded(&self, ray: Ray, max_distance: f64) -> bool {
+        self.bvh
+            .any(ray.origin, ray.direction, max_distance, |index| {
+                self.objects[index].occluded(ray, max_distance)
+            })
     }
 }
@@ -0,0 +1,225 @@
+use std::error::Error;
+
+/// Radiance RGBE (.hdr) image, decoded to linear floats, top row first.
+pub struct RadianceHdr {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<[f64; 3]>,
+}
+
+impl RadianceHdr {
+    pub fn deserialize(buffer: &[u8]) -> Result<RadianceHdr, Box<dyn Error>> {
+        let mut lines = HeaderLines {
+            buffer,
+            position: 0,
+        };
+
+        let magic = lines.next().ok_or("Invalid HDR file format")?;
+        if magic != "#?RADIANCE" && magic != "#?RGBE" {
+            return Err("Invalid HDR file format".into());
+        }
+        loop {
+            let line = lines.next().ok_or("Unexpected end of HDR header")?;
+            if line.is_empty() {
+                break;
+            }
+            if let Some(format) = line.strip_prefix("FORMAT=") {
+                if format != "32-bit_rle_rgbe" {
+                    return Err(format!("Unsupported HDR format: {}", format).into());
+                }
+            }
+        }
+
+        let resolution = lines.next().ok_or("Missing HDR resolution")?;
+        let (flip, height, width) = match resolution.split_whitespace().collect::<Vec<_>>()[..] {
+            ["-Y", height, "+X", width] => (false, height.parse()?, width.parse()?),
+            ["+Y", height, "+X", width] => (true, height.parse()?, width.parse()?),
+            _ => return Err(format!("Unsupported HDR orientation: {}", resolution).into()),
+        };
+
+        if width == 0 || height == 0 {
+            return Err(format!("Invalid HDR resolution: {}", resolution).into());
+        }
+        let size = usize::checked_mul(width, height)
+            .ok_or_else(|| format!("HDR image too large: {}", resolution))?;
+
+        let mut data = &buffer[lines.position..];
+        // runs can make the size much larger than the data, don't trust it
+        let mut pixels = Vec::with_capacity(size.min(data.len()));
+        let mut scanline = vec![[0u8; 4]; width];
+        for _ in 0..height {
+            data = read_scanline(data, &mut scanline)?;
+            pixels.extend(scanline.iter().map(|rgbe| rgbe_to_rgb(*rgbe)));
+        }
+        if flip {
+            let rows: Vec<_> = pixels.chunks(width).rev().flatten().copied().collect();
+            pixels = rows;
+        }
+
+        Ok(RadianceHdr {
+            width,
+            height,
+            pixels,
+        })
+    }
+}
+
+struct HeaderLines<'a> {
+    buffer: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Iterator for HeaderLines<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        let rest = &self.buffer[self.position..];
+        let end = rest.iter().position(|b| *b == b'\n')?;
+        self.position += end + 1;
+        std::str::from_utf8(&rest[..end]).ok().map(str::trim_end)
+    }
+}
+
+/// Reads one scanline in any of the flat, old run-length or new run-length encodings.
+fn read_scanline<'a>(data: &'a [u8], scanline: &mut [[u8; 4]]) -> Result<&'a [u8], Box<dyn Error>> {
+    let width = scanline.len();
+    let is_new_rle = (8..0x8000).contains(&width)
+        && data.len() >= 4
+        && data[0] == 2
+        && data[1] == 2
+        && data[2] & 0x80 == 0;
+    if !is_new_rle {
+        return read_old_scanline(data, scanline);
+    }
+    if ((data[2] as usize) << 8 | data[3] as usize) != width {
+        return Err("HDR scanline width mismatch".into());
+    }
+
+    // each channel is stored separately as runs and literal spans
+    let mut data = &data[4..];
+    for channel in 0..4 {
+        let mut x = 0;
+        while x < width {
+            let (&count, rest) = data.split_first().ok_or("Unexpected end of HDR data")?;
+            if count > 128 {
+                let count = count as usize - 128;
+                let (&value, rest) = rest.split_first().ok_or("Unexpected end of HDR data")?;
+                if x + count > width {
+                    return Err("HDR run overflows scanline".into());
+                }
+                for pixel in &mut scanline[x..x + count] {
+                    pixel[channel] = value;
+                }
+                x += count;
+                data = rest;
+            } else {
+                let count = count as usize;
+                if count == 0 || x + count > width || rest.len() < count {
+                    return Err("Invalid HDR span".into());
+                }
+                for (pixel, value) in scanline[x..x + count].iter_mut().zip(rest) {
+                    pixel[channel] = *value;
+                }
+                x += count;
+                data = &rest[count..];
+            }
+        }
+    }
+    Ok(data)
+}
+
+fn read_old_scanline<'a>(
+    mut data: &'a [u8],
+    scanline: &mut [[u8; 4]],
+) -> Result<&'a [u8], Box<dyn Error>> {
+    let mut x = 0;
+    let mut shift = 0u32;
+    while x < scanline.len() {
+        if data.len() < 4 {
+            return Err("Unexpected end of HDR data".into());
+        }
+        let rgbe = [data[0], data[1], data[2], data[3]];
+        data = &data[4..];
+        if rgbe[..3] == [1, 1, 1] {
+            // repeat the previous pixel, consecutive repeats form more significant digits
+            if shift >= usize::BITS - 8 {
+                return Err("HDR run too long".into());
+            }
+            let count = (rgbe[3] as usize) << shift;
+            if x == 0 || x + count > scanline.len() {
+                return Err("Invalid HDR run".into());
+            }
+            let previous = scanline[x - 1];
+            scanline[x..x + count].fill(previous);
+            x += count;
+            shift += 8;
+        } else {
+            scanline[x] = rgbe;
+            x += 1;
+            shift = 0;
+        }
+    }
+    Ok(data)
+}
+
+fn rgbe_to_rgb([r, g, b, e]: [u8; 4]) -> [f64; 3] {
+    if e == 0 {
+        return [0.0; 3];
+    }
+    let scale = 2f64.powi(e as i32 - 136);
+    [r as f64 * scale, g as f64 * scale, b as f64 * scale]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_run_length_encoded_scanlines() {
+        let mut buffer = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 2 +X 8\n".to_vec();
+        for row in 0..2u8 {
+            buffer.extend([2, 2, 0, 8]);
+            // red as a run, green as a literal span, blue and exponent as runs
+            buffer.extend([128 + 8, 128]);
+            buffer.extend([8, 0, 16, 32, 48, 64, 80, 96, 112 + row]);
+            buffer.extend([128 + 8, 0]);
+            buffer.extend([128 + 8, 129]);
+        }
+
+        let image = RadianceHdr::deserialize(&buffer).unwrap();
+        assert_eq!((image.width, image.height), (8, 2));
+        assert_eq!(image.pixels[0], [1.0, 0.0, 0.0]);
+        assert_eq!(image.pixels[3], [1.0, 0.375, 0.0]);
+        assert_eq!(image.pixels[15], [1.0, 113.0 / 128.0, 0.0]);
+    }
+
+    #[test]
+    fn decodes_flat_scanlines() {
+        let mut buffer = b"#?RADIANCE\n\n+Y 2 +X 1\n".to_vec();
+        buffer.extend([128, 0, 0, 128, 0, 0, 128, 129]);
+
+        let image = RadianceHdr::deserialize(&buffer).unwrap();
+        // +Y stores the bottom row first
+        assert_eq!(image.pixels, vec![[0.0, 0.0, 1.0], [0.5, 0.0, 0.0]]);
+    }
+
+    #[test]
+    fn rejects_malformed_files() {
+        for resolution in ["-Y 0 +X 4", "-Y 4 +X 0", "-Y 4294967296 +X 4294967296"] {
+            let buffer = format!("#?RADIANCE\n\n{}\n", resolution);
+            assert!(RadianceHdr::deserialize(buffer.as_bytes()).is_err());
+        }
+
+        // consecutive repeats of the first pixel, each a digit more significant
+        let mut buffer = b"#?RADIANCE\n\n-Y 1 +X 2\n".to_vec();
+        buffer.extend([128, 0, 0, 128]);
+        for _ in 0..16 {
+            buffer.extend([1, 1, 1, 0]);
+        }
+        assert!(RadianceHdr::deserialize(&buffer).is_err());
+
+        let mut buffer = b"#?RADIANCE\n\n-Y 2 +X 1\n".to_vec();
+        buffer.extend([128, 0, 0, 128]);
+        assert!(RadianceHdr::deserialize(&buffer).is_err());
+    }
+}
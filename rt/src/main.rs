@@ -1,13 +1,28 @@
 use bmp::{MinirtBmp, MinirtBmpPixel};
-use scene::{Image, ImageCache, ImageLoader, Scene};
+use jsonc::Value;
+use scene::{
+    integrator_from_str, mesh::MeshData, tone_mapping_from_str, AssetCache, Image, ImageLoader,
+    MeshLoader, Scene,
+};
+use std::collections::HashMap;
 use std::error::Error;
 use std::io::Write;
 use std::path::Path;
 use std::sync::Arc;
 use std::{env, path::PathBuf};
-use types::{HDRColor, LDRColor};
+use types::HDRColor;
 
-use core::types::math::Vec3;
+use core::random::Rng;
+use core::tonemap::{ToneMapper, ToneMapping};
+use core::types::{math::Vec3, rt::Integrator};
+
+mod hdr;
+mod sampling;
+mod tile;
+
+use hdr::RadianceHdr;
+
+use sampling::{filter_from_str, sampling_from_str, Filter, FilterSampler, Sampling};
 
 #[derive(Debug)]
 struct Args {
@@ -30,11 +45,21 @@ struct Args {
     exposure: Option<f64>,
     ldr: bool,
     no_ldr: bool,
+    max_depth: Option<usize>,
+    integrator: Option<Integrator>,
+    samples: Option<usize>,
+    sampling: Option<Sampling>,
+    filter: Option<Filter>,
+    min_samples: Option<usize>,
+    max_samples: Option<usize>,
+    noise_threshold: Option<f64>,
+    heatmap: Option<String>,
+    tone_mapping: Option<ToneMapping>,
 }
 
 #[derive(Debug)]
 enum ArgsResult {
-    Ok(Args),
+    Ok(Box<Args>),
     Help,
     Version,
 }
@@ -79,6 +104,16 @@ fn args() -> Result<ArgsResult, Box<dyn Error>> {
         exposure: None,
         ldr: false,
         no_ldr: false,
+        max_depth: None,
+        integrator: None,
+        samples: None,
+        sampling: None,
+        filter: None,
+        min_samples: None,
+        max_samples: None,
+        noise_threshold: None,
+        heatmap: None,
+        tone_mapping: None,
     };
     let mut positionals = vec![];
 
@@ -99,10 +134,20 @@ fn args() -> Result<ArgsResult, Box<dyn Error>> {
                 .flat_map(|x| vec![x.0, x.1])
                 .collect();
             let flag = if parts.len() == 2 { parts[0] } else { arg };
+            let is_switch = matches!(
+                flag,
+                "no-output-bmp-suffix"
+                    | "stdout"
+                    | "emit-normal"
+                    | "emit-distance"
+                    | "ldr"
+                    | "no-ldr"
+            );
             let value = if parts.len() == 2 {
                 Some(parts[1].to_string())
             } else {
-                if i + 1 < args.len() && !args[i + 1].starts_with('-') {
+                // switches never take a value, so don't swallow a following positional
+                if !is_switch && i + 1 < args.len() && !args[i + 1].starts_with('-') {
                     i += 1;
                     Some(args[i].clone())
                 } else {
@@ -197,19 +242,77 @@ fn args() -> Result<ArgsResult, Box<dyn Error>> {
                     )?)
                 }
                 "ldr" => {
-                    if result.gamma.is_some() || result.exposure.is_some() || result.no_ldr {
+                    if result.gamma.is_some()
+                        || result.exposure.is_some()
+                        || result.tone_mapping.is_some()
+                        || result.no_ldr
+                    {
                         return Err(
-                            "--ldr and --gamma/--exposure/--no-ldr are mutually exclusive".into(),
+                            "--ldr and --gamma/--exposure/--tone-mapping/--no-ldr are mutually exclusive"
+                                .into(),
                         );
                     }
                     result.ldr = true;
                 }
+                "tone-mapping" => {
+                    if result.ldr {
+                        return Err("--tone-mapping and --ldr are mutually exclusive".into());
+                    }
+                    result.tone_mapping = Some(tone_mapping_from_str(
+                        value.ok_or("Missing --tone-mapping")?.as_str(),
+                    )?)
+                }
                 "no-ldr" => {
                     if result.ldr {
                         return Err("--no-ldr and --ldr are mutually exclusive".into());
                     }
                     result.no_ldr = true;
                 }
+                "max-depth" => {
+                    result.max_depth = Some(parse(
+                        value.ok_or("Missing --max-depth")?.as_str(),
+                        "max-depth",
+                    )?)
+                }
+                "integrator" => {
+                    result.integrator = Some(integrator_from_str(
+                        value.ok_or("Missing --integrator")?.as_str(),
+                    )?)
+                }
+                "samples" => {
+                    result.samples = Some(parse(
+                        value.ok_or("Missing --samples")?.as_str(),
+                        "samples",
+                    )?)
+                }
+                "sampling" => {
+                    result.sampling = Some(sampling_from_str(
+                        value.ok_or("Missing --sampling")?.as_str(),
+                    )?)
+                }
+                "filter" => {
+                    result.filter =
+                        Some(filter_from_str(value.ok_or("Missing --filter")?.as_str())?)
+                }
+                "min-samples" => {
+                    result.min_samples = Some(parse(
+                        value.ok_or("Missing --min-samples")?.as_str(),
+                        "min-samples",
+                    )?)
+                }
+                "max-samples" => {
+                    result.max_samples = Some(parse(
+                        value.ok_or("Missing --max-samples")?.as_str(),
+                        "max-samples",
+                    )?)
+                }
+                "noise-threshold" => {
+                    result.noise_threshold = Some(parse(
+                        value.ok_or("Missing --noise-threshold")?.as_str(),
+                        "noise-threshold",
+                    )?)
+                }
+                "heatmap" => result.heatmap = Some(value.ok_or("Missing --heatmap")?),
                 _ => return Err(format!("Unknown option --{}", flag).into()),
             }
         } else if arg.starts_with('-') && arg.len() > 1 {
@@ -225,7 +328,8 @@ fn args() -> Result<ArgsResult, Box<dyn Error>> {
                         }
                         result.stdout = true;
                     }
-                    'W' | 'H' | 's' | 'a' | 'v' | 'j' | 'g' | 'e' | 'P' | 'D' | 'L' => {
+                    'W' | 'H' | 's' | 'a' | 'v' | 'j' | 'g' | 'e' | 'P' | 'D' | 'L' | 'r' | 'i'
+                    | 'p' => {
                         let mut val: String = chars.collect();
                         if val.is_empty() {
                             i += 1;
@@ -266,13 +370,23 @@ fn args() -> Result<ArgsResult, Box<dyn Error>> {
                                 }
                                 result.camera_look_at = Some(parse_vec3(&val, "-L")?)
                             }
+                            'r' => result.max_depth = Some(parse(&val, "-r")?),
+                            'i' => result.integrator = Some(integrator_from_str(&val)?),
+                            'p' => result.samples = Some(parse(&val, "-p")?),
                             _ => {}
                         }
                         break;
                     }
                     'l' => {
-                        if result.gamma.is_some() || result.exposure.is_some() {
-                            return Err("-l and -g/-e are mutually exclusive".into());
+                        if result.gamma.is_some()
+                            || result.exposure.is_some()
+                            || result.tone_mapping.is_some()
+                            || result.no_ldr
+                        {
+                            return Err(
+                                "-l and -g/-e/--tone-mapping/--no-ldr are mutually exclusive"
+                                    .into(),
+                            );
                         }
                         result.ldr = true;
                     }
@@ -285,6 +399,45 @@ fn args() -> Result<ArgsResult, Box<dyn Error>> {
         i += 1;
     }
 
+    if result.jobs == Some(0) {
+        return Err("--jobs must be at least 1".into());
+    }
+    if result.width == Some(0) || result.height == Some(0) {
+        return Err("--width and --height must be at least 1".into());
+    }
+    if result.samples == Some(0) {
+        return Err("--samples must be at least 1".into());
+    }
+    if result.min_samples == Some(0) || result.max_samples == Some(0) {
+        return Err("--min-samples and --max-samples must be at least 1".into());
+    }
+    if result
+        .noise_threshold
+        .is_some_and(|threshold| threshold < 0.0)
+    {
+        return Err("--noise-threshold must not be negative".into());
+    }
+    if result.heatmap.is_some()
+        && result.min_samples.is_none()
+        && result.max_samples.is_none()
+        && result.noise_threshold.is_none()
+    {
+        // every pixel would take the same number of samples
+        return Err(
+            "--heatmap needs adaptive sampling (--min-samples, --max-samples or --noise-threshold)"
+                .into(),
+        );
+    }
+    if result.super_sampling == Some(0) {
+        return Err("--super-sampling must be at least 1".into());
+    }
+    if result.gamma.is_some_and(|gamma| gamma <= 0.0) {
+        return Err("--gamma must be greater than 0".into());
+    }
+    if result.exposure.is_some_and(|exposure| exposure <= 0.0) {
+        return Err("--exposure must be greater than 0".into());
+    }
+
     if positionals.is_empty() {
         return Err("Missing required input file".into());
     }
@@ -298,29 +451,224 @@ fn args() -> Result<ArgsResult, Box<dyn Error>> {
         return Err("Missing required output file".into());
     }
 
-    Ok(ArgsResult::Ok(result))
+    Ok(ArgsResult::Ok(Box::new(result)))
 }
 
-struct Renderer<'a>(&'a Scene);
-
-impl<'a> Renderer<'a> {
-    fn render(&self, x: usize, y: usize) -> MinirtBmpPixel {
-        let scene = &self.0 .0;
-        let width = scene.image_width as f64;
-        let height = scene.image_height as f64;
-
-        let u = (x as f64 + 0.5) / width;
-        let v = (y as f64 + 0.5) / height;
+/// Sample count limits for adaptive sampling.
+#[derive(Clone, Copy, Debug)]
+struct Adaptive {
+    min_samples: usize,
+    max_samples: usize,
+    /// Pixels stop once the standard error of their mean luminance drops
+    /// below this fraction of the mean.
+    noise_threshold: f64,
+}
 
-        let hdr_color = core::sample(scene, u, v);
-        let color = tmp_hdr_to_ldr(hdr_color);
+struct Renderer<'a> {
+    scene: &'a Scene,
+    super_sampling: usize,
+    sampling: Sampling,
+    filter: FilterSampler,
+    adaptive: Option<Adaptive>,
+}
 
-        MinirtBmpPixel {
+impl<'a> Renderer<'a> {
+    /// Renders a pixel, also returning how many samples it took.
+    fn render(&self, x: usize, y: usize) -> (MinirtBmpPixel, usize) {
+        let mut rng = Rng::new((y * self.scene.0.image_width + x) as u64);
+        let (hdr_color, samples) = match self.adaptive {
+            Some(adaptive) => self.render_adaptive(x, y, adaptive, &mut rng),
+            None => self.render_fixed(x, y, &mut rng),
+        };
+
+        // negative values from filters with negative lobes are clipped here too
+        let color = self.scene.0.tone_mapper.map(hdr_color);
+
+        let pixel = MinirtBmpPixel {
             r: (color.r * 255.0) as u8,
             g: (color.g * 255.0) as u8,
             b: (color.b * 255.0) as u8,
+        };
+        (pixel, samples)
+    }
+
+    /// Traces one camera ray through subpixel `sub_x`, `sub_y` of the n x n
+    /// pattern, which is spread over the filter footprint and may reach into
+    /// the neighbouring pixels.
+    fn sample(
+        &self,
+        x: usize,
+        y: usize,
+        sub_x: usize,
+        sub_y: usize,
+        rng: &mut Rng,
+    ) -> (HDRColor, f64) {
+        let scene = &self.scene.0;
+        let position = self
+            .sampling
+            .position(sub_x, sub_y, self.super_sampling, rng);
+        let (dx, dy, weight) = self.filter.sample(position);
+        let u = (x as f64 + 0.5 + dx) / scene.image_width as f64;
+        let v = (y as f64 + 0.5 + dy) / scene.image_height as f64;
+        (core::sample(scene, u, v, rng), weight)
+    }
+
+    fn render_fixed(&self, x: usize, y: usize, rng: &mut Rng) -> (HDRColor, usize) {
+        let samples = match self.scene.0.integrator {
+            Integrator::Whitted => 1,
+            Integrator::Path => self.scene.0.samples_per_pixel,
+        };
+        let n = self.super_sampling;
+        let mut hdr_color = HDRColor::BLACK;
+        let mut weight_sum = 0.0;
+        for sub_y in 0..n {
+            for sub_x in 0..n {
+                for _ in 0..samples {
+                    let (color, weight) = self.sample(x, y, sub_x, sub_y, rng);
+                    hdr_color = hdr_color + color * weight;
+                    weight_sum += weight;
+                }
+            }
+        }
+        (normalize(hdr_color, weight_sum), n * n * samples)
+    }
+
+    /// Keeps sampling until the pixel's luminance estimate is stable enough,
+    /// cycling through the n x n subpixel pattern.
+    fn render_adaptive(
+        &self,
+        x: usize,
+        y: usize,
+        adaptive: Adaptive,
+        rng: &mut Rng,
+    ) -> (HDRColor, usize) {
+        let n = self.super_sampling;
+        let mut hdr_color = HDRColor::BLACK;
+        let mut weight_sum = 0.0;
+        // Welford's running mean and variance of the sample luminance
+        let mut mean = 0.0;
+        let mut m2 = 0.0;
+        let mut count = 0;
+        while count < adaptive.max_samples {
+            let (color, weight) = self.sample(x, y, count % n, count / n % n, rng);
+            hdr_color = hdr_color + color * weight;
+            weight_sum += weight;
+
+            count += 1;
+            let luminance = 0.2126 * color.r + 0.7152 * color.g + 0.0722 * color.b;
+            let delta = luminance - mean;
+            mean += delta / count as f64;
+            m2 += delta * (luminance - mean);
+
+            if count >= adaptive.min_samples.max(2) {
+                let variance = m2 / (count - 1) as f64;
+                let standard_error = (variance / count as f64).sqrt();
+                // the small floor keeps near-black pixels from sampling forever
+                if standard_error <= adaptive.noise_threshold * mean.max(1e-3) {
+                    break;
+                }
+            }
+        }
+        (normalize(hdr_color, weight_sum), count)
+    }
+}
+
+fn normalize(hdr_color: HDRColor, weight_sum: f64) -> HDRColor {
+    if weight_sum > 0.0 {
+        hdr_color / weight_sum
+    } else {
+        HDRColor::BLACK
+    }
+}
+
+/// Maps a sample count to a blue-green-red ramp between `min` and `max`.
+fn heatmap_pixel(samples: usize, min: usize, max: usize) -> MinirtBmpPixel {
+    let t = if max > min {
+        (samples.clamp(min, max) - min) as f64 / (max - min) as f64
+    } else {
+        0.0
+    };
+    let (r, g, b) = if t < 0.5 {
+        (0.0, t * 2.0, 1.0 - t * 2.0)
+    } else {
+        (t * 2.0 - 1.0, 2.0 - t * 2.0, 0.0)
+    };
+    MinirtBmpPixel {
+        r: (r * 255.0) as u8,
+        g: (g * 255.0) as u8,
+        b: (b * 255.0) as u8,
+    }
+}
+
+fn vec3_to_json_value(value: Vec3) -> Value {
+    Value::Array(vec![
+        Value::Number(value.x),
+        Value::Number(value.y),
+        Value::Number(value.z),
+    ])
+}
+
+/// Returns the JSON object stored under `key`, creating an empty one if missing.
+fn json_object<'a>(
+    dict: &'a mut HashMap<String, Value>,
+    key: &str,
+) -> Result<&'a mut HashMap<String, Value>, String> {
+    match dict
+        .entry(key.to_string())
+        .or_insert_with(|| Value::Object(HashMap::new()))
+    {
+        Value::Object(dict) => Ok(dict),
+        _ => Err(format!("{} must be a JSON object", key)),
+    }
+}
+
+/// Patches the scene JSON with the command line overrides, so they get the
+/// same validation and derived values (like the camera aspect ratio) as the
+/// scene file itself.
+fn apply_overrides(json: &mut Value, a: &Args) -> Result<(), String> {
+    let Value::Object(dict) = json else {
+        return Err("Scene must be a JSON object".to_string());
+    };
+
+    if a.width.is_some() || a.height.is_some() {
+        let image_size = json_object(dict, "imageSize")?;
+        if let Some(width) = a.width {
+            image_size.insert("width".to_string(), Value::Number(width as f64));
+        }
+        if let Some(height) = a.height {
+            image_size.insert("height".to_string(), Value::Number(height as f64));
+        }
+    }
+
+    if a.camera_position.is_some() || a.camera_direction.is_some() || a.camera_look_at.is_some() {
+        let camera = json_object(dict, "camera")?;
+        if let Some(position) = a.camera_position {
+            camera.insert("position".to_string(), vec3_to_json_value(position));
+        }
+        if let Some(direction) = a.camera_direction {
+            camera.remove("lookAt");
+            camera.insert("direction".to_string(), vec3_to_json_value(direction));
         }
+        if let Some(look_at) = a.camera_look_at {
+            camera.remove("direction");
+            camera.insert("lookAt".to_string(), vec3_to_json_value(look_at));
+        }
+    }
+
+    if let Some(ambient_light) = a.ambient_light {
+        dict.insert(
+            "ambientLight".to_string(),
+            vec3_to_json_value(ambient_light),
+        );
+    }
+    if let Some(void_color) = a.void_color {
+        if dict.contains_key("sky") {
+            return Err("--void-color has no effect on scenes with a sky".to_string());
+        }
+        dict.insert("voidColor".to_string(), vec3_to_json_value(void_color));
     }
+
+    Ok(())
 }
 
 fn main() {
@@ -328,16 +676,98 @@ fn main() {
         Ok(ArgsResult::Ok(a)) => {
             if let Err(e) = (|| -> Result<(), String> {
                 let json_content = std::fs::read_to_string(&a.input).map_err(|e| e.to_string())?;
-                let json_value = jsonc::parse(&json_content)?;
-
-                let image_loader = ImageImageLoader::new(".");
-                let mut image_cache = ImageCache::new(&image_loader);
-                let scene = Scene::from_json_value(json_value, &mut image_cache)?;
+                let mut json_value = jsonc::parse(&json_content)?;
+                apply_overrides(&mut json_value, &a)?;
+
+                let image_loader = ImageImageLoader::new(&a.input);
+                let mesh_loader = FileMeshLoader::new(&a.input);
+                let mut assets = AssetCache::new(&image_loader, &mesh_loader);
+                let mut scene = Scene::from_json_value(json_value, &mut assets)?;
+                if let Some(max_depth) = a.max_depth {
+                    scene.0.max_depth = max_depth;
+                }
+                if let Some(integrator) = a.integrator {
+                    scene.0.integrator = integrator;
+                }
+                if let Some(samples) = a.samples {
+                    scene.0.samples_per_pixel = samples;
+                }
+                if a.no_ldr && scene.0.tone_mapper.tone_mapping == ToneMapping::Clamp {
+                    // the scene asks for clipped radiance, compress it instead
+                    scene.0.tone_mapper.tone_mapping = ToneMapper::default().tone_mapping;
+                }
+                if let Some(tone_mapping) = a.tone_mapping {
+                    scene.0.tone_mapper.tone_mapping = tone_mapping;
+                }
+                if let Some(gamma) = a.gamma {
+                    scene.0.tone_mapper.gamma = gamma;
+                    scene.0.tone_mapper.srgb = false;
+                }
+                if let Some(exposure) = a.exposure {
+                    scene.0.tone_mapper.exposure = exposure;
+                }
+                if a.ldr {
+                    // raw radiance, only clipped to the displayable range
+                    scene.0.tone_mapper = ToneMapper {
+                        tone_mapping: ToneMapping::Clamp,
+                        exposure: 1.0,
+                        gamma: 1.0,
+                        srgb: false,
+                        ..ToneMapper::default()
+                    };
+                }
 
-                let r = Renderer(&scene);
-                let bmp = MinirtBmp::new(scene.0.image_width, scene.0.image_height, |x, y| {
-                    r.render(x, y)
-                });
+                let jobs = a
+                    .jobs
+                    .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get()));
+                let adaptive = if a.min_samples.is_some()
+                    || a.max_samples.is_some()
+                    || a.noise_threshold.is_some()
+                {
+                    let min_samples = a.min_samples.unwrap_or(4);
+                    let max_samples = a.max_samples.unwrap_or(64.max(min_samples));
+                    if max_samples < min_samples {
+                        return Err("--max-samples must not be less than --min-samples".to_string());
+                    }
+                    Some(Adaptive {
+                        min_samples,
+                        max_samples,
+                        noise_threshold: a.noise_threshold.unwrap_or(0.01),
+                    })
+                } else {
+                    None
+                };
+                let r = Renderer {
+                    scene: &scene,
+                    super_sampling: a.super_sampling.unwrap_or(1),
+                    sampling: a.sampling.unwrap_or(Sampling::Grid),
+                    filter: FilterSampler::new(a.filter.unwrap_or(Filter::Box)),
+                    adaptive,
+                };
+                let (pixels, samples): (Vec<_>, Vec<_>) =
+                    tile::render(scene.0.image_width, scene.0.image_height, jobs, |x, y| {
+                        r.render(x, y)
+                    })
+                    .into_iter()
+                    .unzip();
+                let bmp = MinirtBmp {
+                    width: scene.0.image_width,
+                    height: scene.0.image_height,
+                    extra: pixels,
+                };
+
+                if let (Some(heatmap), Some(adaptive)) = (&a.heatmap, adaptive) {
+                    let (min, max) = (adaptive.min_samples, adaptive.max_samples);
+                    let heatmap_bmp = MinirtBmp {
+                        width: scene.0.image_width,
+                        height: scene.0.image_height,
+                        extra: samples
+                            .into_iter()
+                            .map(|count| heatmap_pixel(count, min, max))
+                            .collect(),
+                    };
+                    std::fs::write(heatmap, heatmap_bmp.serialize()).map_err(|e| e.to_string())?;
+                }
 
                 let bmp_bytes = bmp.serialize();
                 if a.stdout {
@@ -367,21 +797,6 @@ fn main() {
     }
 }
 
-fn tmp_hdr_to_ldr(color: HDRColor) -> LDRColor {
-    const GAMMA: f64 = 2.2;
-    const EXPOSURE: f64 = 1.0;
-
-    let r = 1.0 - (-color.r * EXPOSURE).exp();
-    let g = 1.0 - (-color.g * EXPOSURE).exp();
-    let b = 1.0 - (-color.b * EXPOSURE).exp();
-
-    LDRColor {
-        r: r.powf(1.0 / GAMMA),
-        g: g.powf(1.0 / GAMMA),
-        b: b.powf(1.0 / GAMMA),
-    }
-}
-
 struct BmpImage {
     image: MinirtBmp,
 }
@@ -418,6 +833,36 @@ impl Image for BmpImage {
     }
 }
 
+struct HdrImage {
+    image: RadianceHdr,
+}
+
+impl HdrImage {
+    fn new(path: &str) -> Result<HdrImage, Box<dyn Error>> {
+        let buffer = std::fs::read(path)?;
+        let image = RadianceHdr::deserialize(&buffer)?;
+        Ok(HdrImage { image })
+    }
+}
+
+impl Image for HdrImage {
+    fn width(&self) -> usize {
+        self.image.width
+    }
+
+    fn height(&self) -> usize {
+        self.image.height
+    }
+
+    fn get(&self, x: usize, y: usize) -> [f64; 3] {
+        if x >= self.width() || y >= self.height() {
+            panic!("Incorrect coord given");
+        }
+
+        self.image.pixels[y * self.image.width + x]
+    }
+}
+
 struct ImageImageLoader {
     scene_dir: PathBuf,
 }
@@ -434,8 +879,117 @@ impl ImageImageLoader {
 }
 
 impl ImageLoader for ImageImageLoader {
-    fn load(&self, path: &str) -> Arc<dyn Image + Send + Sync> {
+    fn load(&self, path: &str) -> Result<Arc<dyn Image + Send + Sync>, String> {
         let full_path = self.scene_dir.join(path);
-        Arc::new(BmpImage::new(full_path.to_str().expect("Invalid path")).expect("Invalid image"))
+        let full_path = full_path.to_str().ok_or("Invalid path")?;
+        let is_hdr = Path::new(path)
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("hdr"));
+        let image: Arc<dyn Image + Send + Sync> = if is_hdr {
+            Arc::new(HdrImage::new(full_path).map_err(|e| format!("Can't load {}: {}", path, e))?)
+        } else {
+            Arc::new(BmpImage::new(full_path).map_err(|e| format!("Can't load {}: {}", path, e))?)
+        };
+        Ok(image)
+    }
+}
+
+/// Reads meshes relative to the scene file, like [`ImageImageLoader`].
+struct FileMeshLoader {
+    scene_dir: PathBuf,
+}
+
+impl FileMeshLoader {
+    fn new<P: AsRef<Path>>(scene_path: P) -> Self {
+        let scene_dir = scene_path
+            .as_ref()
+            .parent()
+            .unwrap_or_else(|| Path::new(""))
+            .to_path_buf();
+        FileMeshLoader { scene_dir }
+    }
+}
+
+impl MeshLoader for FileMeshLoader {
+    fn load(&self, path: &str) -> Result<MeshData, String> {
+        let read = |path: &str| {
+            std::fs::read(self.scene_dir.join(path))
+                .map_err(|e| format!("Can't read {}: {}", path, e))
+        };
+        scene::mesh::from_bytes(path, &read(path)?, read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scene(objects: &str) -> Scene {
+        let json = jsonc::parse(&format!(
+            r#"{{
+                "imageSize": {{ "width": 9, "height": 9 }},
+                "camera": {{ "fov": {{ "max": {{ "degree": 30 }} }}, "position": [0, 0, -5], "lookAt": [0, 0, 0] }},
+                "voidColor": [0.2, 0.4, 0.6], "ambientLight": [0, 0, 0],
+                "integrator": "path",
+                "objects": {}
+            }}"#,
+            objects
+        ))
+        .unwrap();
+        let loader = ImageImageLoader::new("");
+        let mesh_loader = FileMeshLoader::new("");
+        Scene::from_json_value(json, &mut AssetCache::new(&loader, &mesh_loader)).unwrap()
+    }
+
+    fn samples_taken(scene: &Scene, adaptive: Adaptive) -> usize {
+        let renderer = Renderer {
+            scene,
+            super_sampling: 1,
+            sampling: Sampling::Random,
+            filter: FilterSampler::new(Filter::Box),
+            adaptive: Some(adaptive),
+        };
+        renderer.render(4, 4).1
+    }
+
+    #[test]
+    fn adaptive_sampling_stops_on_converged_pixels() {
+        let adaptive = Adaptive {
+            min_samples: 8,
+            max_samples: 256,
+            noise_threshold: 0.01,
+        };
+
+        // every sample sees the same void color, the sphere is behind the camera
+        let empty = scene(
+            r#"[{ "type": "csg", "model": { "type": "sphere", "radius": 1, "position": [0, 0, -10] } }]"#,
+        );
+        assert_eq!(samples_taken(&empty, adaptive), 8);
+
+        // diffuse bounces towards a small light
+        let lit = scene(
+            r#"[
+                { "type": "area", "shape": "sphere", "color": [50, 50, 50], "position": [2, 2, -1], "radius": 0.2 },
+                { "type": "csg", "model": { "type": "sphere", "radius": 1, "position": [0, 0, 0] } }
+            ]"#,
+        );
+        assert!(samples_taken(&lit, adaptive) > 8);
+    }
+
+    #[test]
+    fn missing_images_are_errors() {
+        let json = jsonc::parse(
+            r#"{
+                "imageSize": { "width": 9, "height": 9 },
+                "camera": { "fov": { "max": { "degree": 30 } }, "position": [0, 0, -5], "lookAt": [0, 0, 0] },
+                "voidColor": [0, 0, 0], "ambientLight": [0, 0, 0],
+                "sky": { "type": "image", "path": "missing.hdr" }
+            }"#,
+        )
+        .unwrap();
+        let loader = ImageImageLoader::new("");
+        let mesh_loader = FileMeshLoader::new("");
+        let result = Scene::from_json_value(json, &mut AssetCache::new(&loader, &mesh_loader));
+        assert!(result.is_err_and(|e| e.contains("missing.hdr")));
     }
 }
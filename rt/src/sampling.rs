@@ -0,0 +1,222 @@
+use core::random::Rng;
+
+/// Where subpixel samples are placed inside the filter footprint.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Sampling {
+    /// Centers of an N x N grid.
+    Grid,
+    /// One random point inside each cell of an N x N grid.
+    Stratified,
+    /// N x N random points anywhere in the footprint.
+    Random,
+}
+
+pub fn sampling_from_str(s: &str) -> Result<Sampling, String> {
+    match s {
+        "grid" => Ok(Sampling::Grid),
+        "stratified" => Ok(Sampling::Stratified),
+        "random" => Ok(Sampling::Random),
+        _ => Err(format!("Unknown sampling: {}", s)),
+    }
+}
+
+impl Sampling {
+    /// Position of sample (`sub_x`, `sub_y`) of an `n` x `n` set, in [0, 1).
+    pub fn position(self, sub_x: usize, sub_y: usize, n: usize, rng: &mut Rng) -> (f64, f64) {
+        match self {
+            Sampling::Grid => (
+                (sub_x as f64 + 0.5) / n as f64,
+                (sub_y as f64 + 0.5) / n as f64,
+            ),
+            Sampling::Stratified => (
+                (sub_x as f64 + rng.next_f64()) / n as f64,
+                (sub_y as f64 + rng.next_f64()) / n as f64,
+            ),
+            Sampling::Random => (rng.next_f64(), rng.next_f64()),
+        }
+    }
+}
+
+/// Pixel reconstruction filter, applied separably on both axes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Filter {
+    Box,
+    Tent,
+    Gaussian,
+    /// Mitchell-Netravali with B = C = 1/3.
+    Mitchell,
+}
+
+pub fn filter_from_str(s: &str) -> Result<Filter, String> {
+    match s {
+        "box" => Ok(Filter::Box),
+        "tent" => Ok(Filter::Tent),
+        "gaussian" => Ok(Filter::Gaussian),
+        "mitchell" => Ok(Filter::Mitchell),
+        _ => Err(format!("Unknown filter: {}", s)),
+    }
+}
+
+impl Filter {
+    /// Half width of the filter footprint in pixels.
+    pub fn radius(self) -> f64 {
+        match self {
+            Filter::Box => 0.5,
+            Filter::Tent => 1.0,
+            Filter::Gaussian => 1.5,
+            Filter::Mitchell => 2.0,
+        }
+    }
+
+    fn weight_1d(self, d: f64) -> f64 {
+        let d = d.abs();
+        if d > self.radius() {
+            return 0.0;
+        }
+        match self {
+            Filter::Box => 1.0,
+            Filter::Tent => 1.0 - d,
+            Filter::Gaussian => {
+                const SIGMA: f64 = 0.5;
+                let gaussian = |x: f64| (-x * x / (2.0 * SIGMA * SIGMA)).exp();
+                // shifted so the filter reaches zero at its radius
+                gaussian(d) - gaussian(self.radius())
+            }
+            Filter::Mitchell => {
+                const B: f64 = 1.0 / 3.0;
+                const C: f64 = 1.0 / 3.0;
+                let weight = if d < 1.0 {
+                    (12.0 - 9.0 * B - 6.0 * C) * d.powi(3)
+                        + (-18.0 + 12.0 * B + 6.0 * C) * d.powi(2)
+                        + (6.0 - 2.0 * B)
+                } else {
+                    (-B - 6.0 * C) * d.powi(3)
+                        + (6.0 * B + 30.0 * C) * d.powi(2)
+                        + (-12.0 * B - 48.0 * C) * d
+                        + (8.0 * B + 24.0 * C)
+                };
+                weight / 6.0
+            }
+        }
+    }
+}
+
+const FILTER_TABLE_SIZE: usize = 256;
+
+/// Places samples proportionally to the magnitude of a filter.
+///
+/// Weighting uniformly placed samples by a filter with negative lobes lets the
+/// weight sum get close to zero with few samples. Distributing them by |f|
+/// instead leaves only its sign as the weight, which keeps the sum stable.
+pub struct FilterSampler {
+    filter: Filter,
+    cdf: Vec<f64>,
+}
+
+impl FilterSampler {
+    pub fn new(filter: Filter) -> FilterSampler {
+        let radius = filter.radius();
+        let step = 2.0 * radius / FILTER_TABLE_SIZE as f64;
+        let mut cdf = Vec::with_capacity(FILTER_TABLE_SIZE + 1);
+        cdf.push(0.0);
+        for i in 0..FILTER_TABLE_SIZE {
+            let d = -radius + (i as f64 + 0.5) * step;
+            cdf.push(cdf[i] + filter.weight_1d(d).abs());
+        }
+        FilterSampler { filter, cdf }
+    }
+
+    pub fn radius(&self) -> f64 {
+        self.filter.radius()
+    }
+
+    /// Maps a position in [0, 1)^2 to a pixel offset and the sample weight.
+    pub fn sample(&self, (x, y): (f64, f64)) -> (f64, f64, f64) {
+        let dx = self.offset(x);
+        let dy = self.offset(y);
+        let sign = |w: f64| if w < 0.0 { -1.0 } else { 1.0 };
+        let weight = sign(self.filter.weight_1d(dx)) * sign(self.filter.weight_1d(dy));
+        (dx, dy, weight)
+    }
+
+    fn offset(&self, u: f64) -> f64 {
+        let radius = self.radius();
+        if self.filter == Filter::Box {
+            return (u * 2.0 - 1.0) * radius;
+        }
+
+        let target = u * self.cdf[FILTER_TABLE_SIZE];
+        let i = self
+            .cdf
+            .partition_point(|c| *c <= target)
+            .clamp(1, FILTER_TABLE_SIZE)
+            - 1;
+        let width = self.cdf[i + 1] - self.cdf[i];
+        let t = if width > 0.0 {
+            (target - self.cdf[i]) / width
+        } else {
+            0.5
+        };
+        -radius + (i as f64 + t) * 2.0 * radius / FILTER_TABLE_SIZE as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FILTERS: [Filter; 4] = [
+        Filter::Box,
+        Filter::Tent,
+        Filter::Gaussian,
+        Filter::Mitchell,
+    ];
+
+    #[test]
+    fn weights_match_reference_formulas() {
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-12;
+        let gaussian = |d: f64| (-2.0 * d * d).exp() - (-2.0 * 1.5 * 1.5f64).exp();
+        for (filter, d, expected) in [
+            (Filter::Box, 0.0, 1.0),
+            (Filter::Box, 0.49, 1.0),
+            (Filter::Box, 0.51, 0.0),
+            (Filter::Tent, 0.0, 1.0),
+            (Filter::Tent, -0.25, 0.75),
+            (Filter::Tent, 1.5, 0.0),
+            (Filter::Gaussian, 0.0, gaussian(0.0)),
+            (Filter::Gaussian, 0.7, gaussian(0.7)),
+            (Filter::Gaussian, 1.5, 0.0),
+            (Filter::Mitchell, 0.0, 8.0 / 9.0),
+            (Filter::Mitchell, 1.0, 1.0 / 18.0),
+            // the negative lobe
+            (Filter::Mitchell, -1.5, -5.0 / 144.0),
+            (Filter::Mitchell, 2.0, 0.0),
+            (Filter::Mitchell, 2.5, 0.0),
+        ] {
+            let weight = filter.weight_1d(d);
+            assert!(close(weight, expected), "{:?}({}) = {}", filter, d, weight);
+        }
+    }
+
+    #[test]
+    fn offsets_stay_within_the_radius() {
+        for filter in FILTERS {
+            let sampler = FilterSampler::new(filter);
+            let radius = filter.radius();
+            let mut previous = -radius;
+            for i in 0..1000 {
+                let (dx, dy, weight) = sampler.sample((i as f64 / 1000.0, 0.5));
+                assert!(dx >= previous && dx <= radius, "{:?} {}", filter, dx);
+                assert!(dy.abs() <= radius);
+                assert!(weight == 1.0 || weight == -1.0);
+                previous = dx;
+            }
+        }
+
+        // the far ends of Mitchell's footprint are in the negative lobes
+        let sampler = FilterSampler::new(Filter::Mitchell);
+        assert_eq!(sampler.sample((0.001, 0.5)).2, -1.0);
+        assert_eq!(sampler.sample((0.999, 0.5)).2, -1.0);
+        assert_eq!(sampler.sample((0.5, 0.5)).2, 1.0);
+    }
+}
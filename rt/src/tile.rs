@@ -0,0 +1,116 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+const TILE_SIZE: usize = 32;
+
+/// Renders a `width` x `height` image in row-major order, splitting it into
+/// square tiles that `jobs` worker threads pick up one at a time.
+///
+/// Every pixel is computed by `pixel(x, y)` independently, so the result
+/// doesn't depend on the number of threads or the order tiles finish in.
+pub fn render<T, F>(width: usize, height: usize, jobs: usize, pixel: F) -> Vec<T>
+where
+    T: Send,
+    F: Fn(usize, usize) -> T + Sync,
+{
+    let tiles_x = width.div_ceil(TILE_SIZE);
+    let tiles_y = height.div_ceil(TILE_SIZE);
+    let tile_count = tiles_x * tiles_y;
+
+    let next_tile = AtomicUsize::new(0);
+    let finished = Mutex::new(Vec::with_capacity(tile_count));
+
+    std::thread::scope(|s| {
+        for _ in 0..jobs.clamp(1, tile_count.max(1)) {
+            s.spawn(|| loop {
+                let tile = next_tile.fetch_add(1, Ordering::Relaxed);
+                if tile >= tile_count {
+                    break;
+                }
+                let x0 = tile % tiles_x * TILE_SIZE;
+                let y0 = tile / tiles_x * TILE_SIZE;
+                let x1 = (x0 + TILE_SIZE).min(width);
+                let y1 = (y0 + TILE_SIZE).min(height);
+
+                let mut pixels = Vec::with_capacity((x1 - x0) * (y1 - y0));
+                for y in y0..y1 {
+                    for x in x0..x1 {
+                        pixels.push(pixel(x, y));
+                    }
+                }
+                finished.lock().unwrap().push((tile, pixels));
+            });
+        }
+    });
+
+    let mut tiles: Vec<Option<Vec<T>>> = (0..tile_count).map(|_| None).collect();
+    for (tile, pixels) in finished.into_inner().unwrap() {
+        tiles[tile] = Some(pixels);
+    }
+
+    // stitch the tiles back into rows
+    let mut tiles: Vec<_> = tiles
+        .into_iter()
+        .map(|pixels| pixels.unwrap().into_iter())
+        .collect();
+    let mut result = Vec::with_capacity(width * height);
+    for y in 0..height {
+        let row = y / TILE_SIZE * tiles_x;
+        for (tile_x, tile) in tiles[row..row + tiles_x].iter_mut().enumerate() {
+            let tile_width = (width - tile_x * TILE_SIZE).min(TILE_SIZE);
+            result.extend(tile.by_ref().take(tile_width));
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        sampling::{Filter, FilterSampler, Sampling},
+        FileMeshLoader, ImageImageLoader, Renderer,
+    };
+    use scene::{AssetCache, Scene};
+
+    #[test]
+    fn result_does_not_depend_on_jobs() {
+        // partial tiles on both axes, and path tracing so every pixel draws
+        // random numbers
+        let json = jsonc::parse(
+            r#"{
+                "imageSize": { "width": 70, "height": 45 },
+                "camera": { "fov": { "max": { "degree": 60 } }, "position": [0, 2, -5], "lookAt": [0, 0, 0] },
+                "voidColor": [0.1, 0.1, 0.1], "ambientLight": [0, 0, 0],
+                "integrator": "path", "samplesPerPixel": 2,
+                "objects": [
+                    { "type": "area", "shape": "sphere", "color": [20, 20, 20], "position": [0, 3, 0], "radius": 0.5 },
+                    { "type": "csg", "model": { "type": "sphere", "radius": 1, "position": [0, 0, 0] } },
+                    { "type": "csg", "model": { "type": "cube", "size": [10, 0.2, 10], "position": [0, -1.1, 0] } }
+                ]
+            }"#,
+        )
+        .unwrap();
+        let loader = ImageImageLoader::new("");
+        let mesh_loader = FileMeshLoader::new("");
+        let scene =
+            Scene::from_json_value(json, &mut AssetCache::new(&loader, &mesh_loader)).unwrap();
+        let renderer = Renderer {
+            scene: &scene,
+            super_sampling: 2,
+            sampling: Sampling::Stratified,
+            filter: FilterSampler::new(Filter::Mitchell),
+            adaptive: None,
+        };
+        let (width, height) = (scene.0.image_width, scene.0.image_height);
+
+        let expected: Vec<_> = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| renderer.render(x, y))
+            .collect();
+        for jobs in [1, 3, 8] {
+            let pixels = render(width, height, jobs, |x, y| renderer.render(x, y));
+            assert!(pixels == expected, "{} jobs", jobs);
+        }
+    }
+}
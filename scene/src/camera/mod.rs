@@ -7,7 +7,8 @@ pub mod perspective;
 /// Parse a camera directly from a JSON value.
 pub fn from_json_value(
     json: &Value,
-    screen_aspect_ratio: f64,
+    image_width: usize,
+    image_height: usize,
 ) -> Result<Box<dyn Camera + Send + Sync>, String> {
-    persp::from_json_value(json, screen_aspect_ratio)
+    persp::from_json_value(json, image_width, image_height)
 }
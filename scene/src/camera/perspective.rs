@@ -1,6 +1,6 @@
 use core::types::{
     math::{Direction, Position, Vec3},
-    rt::{Camera, Ray},
+    rt::{Camera, Ray, RayCone},
 };
 use jsonc::Value;
 
@@ -12,6 +12,8 @@ struct PerspectiveCamera {
     direction: Direction,
     right: Vec3,
     up: Vec3,
+    /// Size of a pixel on the image plane at distance 1.
+    pixel_size: f64,
 }
 
 impl Camera for PerspectiveCamera {
@@ -19,9 +21,15 @@ impl Camera for PerspectiveCamera {
         let dir_x = (2.0 * x - 1.0) * self.tan_half_fov_x;
         let dir_z = (1.0 - 2.0 * y) * self.tan_half_fov_y;
         let direction = Direction::new(*self.direction + dir_x * self.right + self.up * dir_z);
+        // pixels away from the center are farther and seen at an angle
+        let distance_squared = 1.0 + dir_x * dir_x + dir_z * dir_z;
         Ray {
             origin: self.position,
             direction,
+            cone: RayCone {
+                width: 0.0,
+                spread: self.pixel_size / distance_squared,
+            },
         }
     }
 }
@@ -41,8 +49,10 @@ enum FovMode {
 
 pub fn from_json_value(
     json: &Value,
-    screen_aspect_ratio: f64,
+    image_width: usize,
+    image_height: usize,
 ) -> Result<Box<dyn Camera + Send + Sync>, String> {
+    let screen_aspect_ratio = image_width as f64 / image_height as f64;
     let dict = match json {
         Value::Object(dict) => dict,
         _ => return Err("Camera must be a JSON object".to_string()),
@@ -111,6 +121,7 @@ pub fn from_json_value(
         direction,
         right,
         up,
+        pixel_size: 2.0 * tan_half_fov_y / image_height as f64,
     }))
 }
 
@@ -1,15 +1,24 @@
 use std::{collections::HashMap, sync::Arc};
 
-use core::types::{
-    math::{Direction, Position, Vec3},
-    rt::{RTObject, Scene as CoreScene},
+use core::{
+    bvh::Bvh,
+    tonemap::{ToneMapper, ToneMapping},
+    types::{
+        math::{Direction, Matrix4, Position, Transform, Vec3},
+        rt::{Integrator, RTObject, Scene as CoreScene},
+    },
 };
 use jsonc::Value;
+use mesh::MeshData;
+use object::instance::Definition;
+use texture::mipmap::MipMap;
 use types::{HDRColor, LDRColor};
 
 pub mod camera;
 pub mod light;
+pub mod mesh;
 pub mod object;
+pub mod sky;
 pub mod texture;
 
 pub struct Scene(pub CoreScene);
@@ -29,7 +38,7 @@ impl From<Scene> for CoreScene {
 impl Scene {
     pub fn from_json_value<T: ImageLoader>(
         json: Value,
-        image_cache: &mut ImageCache<T>,
+        assets: &mut AssetCache<T>,
     ) -> Result<Self, String> {
         let dict = match json {
             Value::Object(dict) => dict,
@@ -61,22 +70,71 @@ impl Scene {
             _ => return Err("imageSize must be a JSON object".to_string()),
         };
 
-        let screen_aspect_ratio = image_width as f64 / image_height as f64;
         let camera_json = dict.get("camera").ok_or("Missing required field: camera")?;
-        let camera = camera::from_json_value(camera_json, screen_aspect_ratio)?;
+        let camera = camera::from_json_value(camera_json, image_width, image_height)?;
 
         let void_color = hdr_color_from_json_value(
             dict.get("voidColor")
                 .ok_or("Missing required field: voidColor")?,
         )?;
 
+        // an explicit sky replaces the constant void color
+        let (sky_color, sun): (sky::Sky, _) = match dict.get("sky") {
+            Some(json) => sky::from_json_value(json, assets)?,
+            None => (Arc::new(move |_| void_color), None),
+        };
+
         let ambient_light = hdr_color_from_json_value(
             dict.get("ambientLight")
                 .ok_or("Missing required field: ambientLight")?,
         )?;
 
+        let max_depth = match dict.get("maxDepth") {
+            Some(Value::Number(n)) if *n >= 0.0 && n.fract() == 0.0 => *n as usize,
+            Some(_) => return Err("maxDepth must be a non-negative integer".to_string()),
+            None => 5,
+        };
+
+        let integrator = match dict.get("integrator") {
+            Some(Value::String(s)) => integrator_from_str(s)?,
+            Some(_) => return Err("integrator must be a string".to_string()),
+            None => Integrator::Whitted,
+        };
+
+        let samples_per_pixel = match dict.get("samplesPerPixel") {
+            Some(Value::Number(n)) if *n >= 1.0 && n.fract() == 0.0 => *n as usize,
+            Some(_) => return Err("samplesPerPixel must be a positive integer".to_string()),
+            None => 16,
+        };
+
+        let light_samples = match dict.get("lightSamples") {
+            Some(Value::Number(n)) if *n >= 1.0 && n.fract() == 0.0 => *n as usize,
+            Some(_) => return Err("lightSamples must be a positive integer".to_string()),
+            None => 16,
+        };
+
+        let tone_mapper = dict
+            .get("hdr")
+            .map(tone_mapper_from_json_value)
+            .unwrap_or(Ok(ToneMapper::default()))?;
+
         let mut objects: Vec<Box<dyn RTObject + Send + Sync>> = Vec::new();
         let mut lights: Vec<Box<dyn core::types::rt::Light + Send + Sync>> = Vec::new();
+        let mut sampled_emission = Vec::new();
+        lights.extend(sun);
+
+        // models parsed once, for any number of instances
+        let mut definitions: HashMap<String, Definition> = HashMap::new();
+        match dict.get("definitions") {
+            Some(Value::Object(models)) => {
+                for (name, model) in models {
+                    let object = object::from_json_value(model, assets)?;
+                    definitions.insert(name.clone(), Arc::from(object));
+                }
+            }
+            Some(_) => return Err("definitions must be a JSON object".to_string()),
+            None => {}
+        }
 
         if let Some(objects_json) = dict.get("objects") {
             match objects_json {
@@ -85,15 +143,33 @@ impl Scene {
                         if let Value::Object(item_dict) = item {
                             if let Some(Value::String(type_str)) = item_dict.get("type") {
                                 match type_str.as_str() {
-                                    "point" | "directional" | "spot" => {
+                                    "point" | "directional" | "spot" | "area" => {
                                         let light = light::from_json_value(item)?;
                                         lights.push(light);
                                     }
-                                    "csg" => {
-                                        let model = item_dict
-                                            .get("model")
-                                            .ok_or("Missing required field: model")?;
-                                        let object = object::from_json_value(model, image_cache)?;
+                                    "csg" | "instance" => {
+                                        let object = if type_str == "csg" {
+                                            let model = item_dict
+                                                .get("model")
+                                                .ok_or("Missing required field: model")?;
+                                            let object = object::from_json_value(model, assets)?;
+                                            match item_dict.get("transform") {
+                                                Some(json) => object::transformed::new(
+                                                    object,
+                                                    transform_from_json_value(json)?,
+                                                ),
+                                                None => object,
+                                            }
+                                        } else {
+                                            object::instance::from_json_value(
+                                                item_dict,
+                                                &definitions,
+                                                assets,
+                                            )?
+                                        };
+                                        let emitter = object.emitter();
+                                        sampled_emission.push(emitter.is_some());
+                                        lights.extend(emitter);
                                         objects.push(object);
                                     }
                                     _ => return Err(format!("Unknown object type: {}", type_str)),
@@ -110,15 +186,76 @@ impl Scene {
             }
         }
 
-        Ok(Scene(CoreScene {
+        let mut scene = CoreScene {
             image_width,
             image_height,
             camera,
             objects,
             lights,
-            sky_color: Arc::new(move |_| void_color),
+            sampled_emission,
+            sky_color,
             ambient_light,
-        }))
+            max_depth,
+            integrator,
+            samples_per_pixel,
+            light_samples,
+            tone_mapper,
+            bvh: Bvh::new(&[]),
+        };
+        scene.build_bvh();
+
+        Ok(Scene(scene))
+    }
+}
+
+pub fn tone_mapping_from_str(s: &str) -> Result<ToneMapping, String> {
+    match s {
+        "exponential" => Ok(ToneMapping::Exponential),
+        "reinhard" => Ok(ToneMapping::Reinhard),
+        "extendedReinhard" => Ok(ToneMapping::ExtendedReinhard),
+        "aces" => Ok(ToneMapping::Aces),
+        "clamp" => Ok(ToneMapping::Clamp),
+        _ => Err(format!("Unknown tone mapping: {}", s)),
+    }
+}
+
+fn tone_mapper_from_json_value(json: &Value) -> Result<ToneMapper, String> {
+    let Value::Object(dict) = json else {
+        return Err("hdr must be a JSON object".to_string());
+    };
+    let mut result = ToneMapper::default();
+
+    if let Some(json) = dict.get("toneMapping") {
+        let Value::String(s) = json else {
+            return Err("toneMapping must be a string".to_string());
+        };
+        result.tone_mapping = tone_mapping_from_str(s)?;
+    }
+    for (key, field) in [
+        ("gamma", &mut result.gamma),
+        ("exposure", &mut result.exposure),
+        ("whitePoint", &mut result.white_point),
+    ] {
+        match dict.get(key) {
+            Some(Value::Number(n)) if *n > 0.0 => *field = *n,
+            Some(_) => return Err(format!("{} must be a positive number", key)),
+            None => {}
+        }
+    }
+    match dict.get("srgb") {
+        Some(Value::Bool(b)) => result.srgb = *b,
+        Some(_) => return Err("srgb must be a boolean".to_string()),
+        None => {}
+    }
+
+    Ok(result)
+}
+
+pub fn integrator_from_str(s: &str) -> Result<Integrator, String> {
+    match s {
+        "whitted" => Ok(Integrator::Whitted),
+        "path" => Ok(Integrator::Path),
+        _ => Err(format!("Unknown integrator: {}", s)),
     }
 }
 
@@ -129,31 +266,61 @@ pub trait Image {
 }
 
 pub trait ImageLoader {
-    fn load(&self, path: &str) -> Arc<dyn Image + Send + Sync>;
+    fn load(&self, path: &str) -> Result<Arc<dyn Image + Send + Sync>, String>;
+}
+
+/// Supplies the meshes a scene names, for example from files next to it
+/// (see [`mesh::from_bytes`]) or from memory with [`mesh::MemoryMeshLoader`].
+pub trait MeshLoader {
+    fn load(&self, path: &str) -> Result<MeshData, String>;
 }
 
-pub struct ImageCache<'a, T: ImageLoader> {
+/// Loads every image once, along with its lazily built mip pyramid, and every mesh once.
+pub struct AssetCache<'a, T: ImageLoader> {
     loader: &'a T,
-    cache: HashMap<String, Arc<dyn Image + Send + Sync>>,
+    mesh_loader: &'a dyn MeshLoader,
+    cache: HashMap<String, Arc<MipMap>>,
+    meshes: HashMap<String, Arc<MeshData>>,
 }
 
-impl<'a, T: ImageLoader> ImageCache<'a, T> {
-    pub fn new(loader: &'a T) -> ImageCache<'a, T> {
-        ImageCache {
+impl<'a, T: ImageLoader> AssetCache<'a, T> {
+    pub fn new(loader: &'a T, mesh_loader: &'a dyn MeshLoader) -> AssetCache<'a, T> {
+        AssetCache {
             loader,
+            mesh_loader,
             cache: HashMap::new(),
+            meshes: HashMap::new(),
         }
     }
 
-    pub fn load(&mut self, path: &str) -> Arc<dyn Image + Send + Sync> {
-        if let Some(image) = self.cache.get(path) {
-            return image.clone();
+    pub fn load(&mut self, path: &str) -> Result<Arc<dyn Image + Send + Sync>, String> {
+        Ok(self.load_mipmap(path)?.image().clone())
+    }
+
+    pub fn load_mipmap(&mut self, path: &str) -> Result<Arc<MipMap>, String> {
+        if let Some(mipmap) = self.cache.get(path) {
+            return Ok(mipmap.clone());
         }
 
-        let loaded_image: Arc<dyn Image + Send + Sync> = self.loader.load(path);
-        self.cache.insert(path.to_string(), loaded_image.clone());
+        let image = self.loader.load(path)?;
+        if image.width() == 0 || image.height() == 0 {
+            return Err(format!("{} is empty", path));
+        }
+        let mipmap = Arc::new(MipMap::new(image));
+        self.cache.insert(path.to_string(), mipmap.clone());
 
-        loaded_image
+        Ok(mipmap)
+    }
+
+    pub fn load_mesh(&mut self, path: &str) -> Result<Arc<MeshData>, String> {
+        if let Some(mesh) = self.meshes.get(path) {
+            return Ok(mesh.clone());
+        }
+
+        let mesh = Arc::new(self.mesh_loader.load(path)?);
+        self.meshes.insert(path.to_string(), mesh.clone());
+
+        Ok(mesh)
     }
 }
 
@@ -284,3 +451,137 @@ fn scale_from_json_value(json: &Value) -> Result<Vec3, String> {
     };
     Ok(Vec3::new(*x, *y, *z))
 }
+
+/// Parse a `transform`: one step or an array of them, applied in order.
+///
+/// A step translates, scales, rotates by Euler angles or around an axis, or
+/// is a raw matrix.
+pub fn transform_from_json_value(json: &Value) -> Result<Transform, String> {
+    let matrix = match json {
+        Value::Array(steps) => steps.iter().try_fold(Matrix4::IDENTITY, |matrix, step| {
+            Ok::<_, String>(transform_step_from_json_value(step)? * matrix)
+        })?,
+        _ => transform_step_from_json_value(json)?,
+    };
+    Transform::new(matrix).ok_or("transform must be affine and invertible".to_string())
+}
+
+fn transform_step_from_json_value(json: &Value) -> Result<Matrix4, String> {
+    let Value::Object(dict) = json else {
+        return Err("transform must be a JSON object or an array of them".to_string());
+    };
+    let has = |keys: &[&str]| keys.iter().any(|key| dict.contains_key(*key));
+    let is_translate = has(&["translate", "translateX", "translateY", "translateZ"]);
+    let is_scale = has(&["scale", "scaleX", "scaleY", "scaleZ"]);
+    let is_rotate = has(&["rotate", "rotateX", "rotateY", "rotateZ"]);
+    let is_matrix = has(&["matrix"]);
+    if [is_translate, is_scale, is_rotate, is_matrix]
+        .iter()
+        .filter(|is| **is)
+        .count()
+        != 1
+    {
+        return Err(
+            "transform step must have exactly one of translate, scale, rotate or matrix"
+                .to_string(),
+        );
+    }
+
+    // the X, Y and Z forms of translate and scale
+    let components = |prefix: &str, default: f64| {
+        let component = |axis: &str| match dict.get(&format!("{}{}", prefix, axis)) {
+            Some(Value::Number(n)) => Ok(*n),
+            Some(_) => Err(format!("{}{} must be a number", prefix, axis)),
+            None => Ok(default),
+        };
+        Ok::<_, String>(Vec3::new(component("X")?, component("Y")?, component("Z")?))
+    };
+
+    if is_translate {
+        let offset = match dict.get("translate") {
+            Some(json) => scale_from_json_value(json)?,
+            None => components("translate", 0.0)?,
+        };
+        Ok(Matrix4::translation(offset))
+    } else if is_scale {
+        let factor = match dict.get("scale") {
+            Some(json) => scale_from_json_value(json)?,
+            None => components("scale", 1.0)?,
+        };
+        Ok(Matrix4::scale(factor))
+    } else if is_rotate {
+        let rotation = match dict.get("rotate") {
+            // yaw around Y, pitch around X and roll around Z, applied roll first
+            Some(Value::Array(angles)) if angles.len() == 3 => {
+                Matrix4::rotation(Direction::new(Vec3::Y), angle_from_json_value(&angles[0])?)
+                    * Matrix4::rotation(Direction::new(Vec3::X), angle_from_json_value(&angles[1])?)
+                    * Matrix4::rotation(Direction::new(Vec3::Z), angle_from_json_value(&angles[2])?)
+            }
+            Some(Value::Object(axis_angle)) => {
+                let axis = direction_from_json_value(
+                    axis_angle
+                        .get("axis")
+                        .ok_or("Missing required field: axis")?,
+                )?;
+                let angle = angle_from_json_value(
+                    axis_angle
+                        .get("angle")
+                        .ok_or("Missing required field: angle")?,
+                )?;
+                Matrix4::rotation(axis, angle)
+            }
+            Some(_) => {
+                return Err(
+                    "rotate must be an array of 3 angles or an object with axis and angle"
+                        .to_string(),
+                )
+            }
+            None => [
+                ("rotateX", Vec3::X),
+                ("rotateY", Vec3::Y),
+                ("rotateZ", Vec3::Z),
+            ]
+            .into_iter()
+            .try_fold(Matrix4::IDENTITY, |matrix, (key, axis)| {
+                Ok::<_, String>(match dict.get(key) {
+                    Some(json) => {
+                        Matrix4::rotation(Direction::new(axis), angle_from_json_value(json)?)
+                            * matrix
+                    }
+                    None => matrix,
+                })
+            })?,
+        };
+        match dict.get("origin") {
+            Some(json) => {
+                let origin = *position_from_json_value(json)?;
+                Ok(Matrix4::translation(origin) * rotation * Matrix4::translation(-origin))
+            }
+            None => Ok(rotation),
+        }
+    } else {
+        let error = || "matrix must be an array of 3 or 4 rows of 4 numbers".to_string();
+        let Some(Value::Array(rows)) = dict.get("matrix") else {
+            return Err(error());
+        };
+        if rows.len() != 3 && rows.len() != 4 {
+            return Err(error());
+        }
+        let mut matrix = Matrix4::IDENTITY;
+        for (i, row) in rows.iter().enumerate() {
+            let Value::Array(row) = row else {
+                return Err(error());
+            };
+            if row.len() != 4 {
+                return Err(error());
+            }
+            for (j, value) in row.iter().enumerate() {
+                let Value::Number(n) = value else {
+                    return Err(error());
+                };
+                matrix.0[i][j] = *n;
+            }
+        }
+        Ok(matrix)
+    }
+}
@@ -0,0 +1,272 @@
+use std::f64::consts::PI;
+
+use core::{
+    random::Rng,
+    types::{
+        math::{Direction, Position, Vec3},
+        rt::Light,
+    },
+};
+use types::HDRColor;
+
+#[derive(Clone, Debug)]
+pub enum Shape {
+    /// Emits from the side `normal` points to; `right` and `up` are half extents.
+    Rectangle {
+        normal: Direction,
+        right: Vec3,
+        up: Vec3,
+    },
+    /// Emits from the side `normal` points to.
+    Disk { normal: Direction, radius: f64 },
+    /// Emits in all directions.
+    Sphere { radius: f64 },
+}
+
+/// Light with a surface, casting soft shadows.
+///
+/// `color` is the intensity as for a point light: the emitted radiance is
+/// `color` divided by the (projected) area, so the light is as bright as a
+/// point light of the same color from afar.
+#[derive(Clone, Debug)]
+pub struct AreaLight {
+    color: HDRColor,
+    position: Position,
+    shape: Shape,
+}
+
+impl AreaLight {
+    pub fn new(color: HDRColor, position: Position, shape: Shape) -> Self {
+        AreaLight {
+            color,
+            position,
+            shape,
+        }
+    }
+
+    /// Light from a point `(u, v)` of the emitter, weighted for one of `count` samples.
+    fn sample_at(
+        &self,
+        position: Position,
+        (u, v): (f64, f64),
+        count: usize,
+    ) -> Option<(HDRColor, Direction, f64)> {
+        let (normal, point) = match &self.shape {
+            Shape::Rectangle { normal, right, up } => (
+                *normal,
+                *self.position + *right * (u * 2.0 - 1.0) + *up * (v * 2.0 - 1.0),
+            ),
+            Shape::Disk { normal, radius } => {
+                let (tangent, bitangent) = normal.tangent_frame();
+                let r = radius * u.sqrt();
+                let phi = 2.0 * PI * v;
+                (
+                    *normal,
+                    *self.position + (tangent * phi.cos() + bitangent * phi.sin()) * r,
+                )
+            }
+            Shape::Sphere { radius } => {
+                return self.sample_sphere(position, *radius, (u, v), count)
+            }
+        };
+
+        let (direction, distance) = (Position::new(point) - position).direction_and_length();
+        let cos_light = normal.dot(-direction);
+        if cos_light <= 0.0 {
+            return None;
+        }
+        // radiance color / area over pdf 1 / area, converted from area to solid angle
+        let weight = cos_light / (distance * distance * count as f64);
+        Some((self.color * weight, direction, distance))
+    }
+
+    /// Samples the cone of directions the sphere covers, which unlike its
+    /// area never wastes samples on the far side.
+    fn sample_sphere(
+        &self,
+        position: Position,
+        radius: f64,
+        (u, v): (f64, f64),
+        count: usize,
+    ) -> Option<(HDRColor, Direction, f64)> {
+        let (axis, center_distance) = (self.position - position).direction_and_length();
+        if center_distance <= radius {
+            return None;
+        }
+
+        let sin2_max = (radius / center_distance).powi(2);
+        let cos_max = (1.0 - sin2_max).sqrt();
+        let cos_theta = 1.0 - u * (1.0 - cos_max);
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * v;
+        let (tangent, bitangent) = axis.tangent_frame();
+        let direction = Direction::new(
+            tangent * (sin_theta * phi.cos())
+                + bitangent * (sin_theta * phi.sin())
+                + *axis * cos_theta,
+        );
+        // stop short of the surface so an emissive sphere doesn't shadow itself
+        let distance = center_distance * cos_theta
+            - (radius * radius - center_distance * center_distance * sin_theta * sin_theta)
+                .max(0.0)
+                .sqrt()
+            - 1e-3;
+
+        // radiance color / (pi r²) over pdf 1 / (2 pi (1 - cos_max))
+        let weight = 2.0 * (1.0 - cos_max) / (radius * radius * count as f64);
+        Some((self.color * weight, direction, distance))
+    }
+}
+
+impl Light for AreaLight {
+    /// Treats the light as a point at its center.
+    fn test(&self, position: Position) -> Option<(HDRColor, Direction, f64)> {
+        let (direction, distance) = (self.position - position).direction_and_length();
+        let cos_light = match &self.shape {
+            Shape::Rectangle { normal, .. } | Shape::Disk { normal, .. } => normal.dot(-direction),
+            Shape::Sphere { .. } => 1.0,
+        };
+        if cos_light <= 0.0 {
+            return None;
+        }
+        Some((
+            self.color * (cos_light / (distance * distance)),
+            direction,
+            distance,
+        ))
+    }
+
+    fn sample(
+        &self,
+        position: Position,
+        count: usize,
+        rng: &mut Rng,
+    ) -> Vec<(HDRColor, Direction, f64)> {
+        let points = rng.stratified_2d(count);
+        let count = points.len();
+        points
+            .into_iter()
+            .filter_map(|point| self.sample_at(position, point, count))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lights() -> [AreaLight; 3] {
+        let white = HDRColor::new(1.0, 1.0, 1.0);
+        let center = Position::new(Vec3::new(0.0, 1.0, 0.0));
+        let down = Direction::new(Vec3::new(0.0, -1.0, 0.0));
+        [
+            AreaLight::new(
+                white,
+                center,
+                Shape::Rectangle {
+                    normal: down,
+                    right: Vec3::new(1.0, 0.0, 0.0),
+                    up: Vec3::new(0.0, 0.0, 0.5),
+                },
+            ),
+            AreaLight::new(
+                white,
+                center,
+                Shape::Disk {
+                    normal: down,
+                    radius: 1.0,
+                },
+            ),
+            AreaLight::new(white, center, Shape::Sphere { radius: 0.5 }),
+        ]
+    }
+
+    #[test]
+    fn samples_lie_on_the_light() {
+        let origin = Position::new(Vec3::ZERO);
+        for light in lights() {
+            let samples = light.sample(origin, 64, &mut Rng::new(1));
+            assert_eq!(samples.len(), 64);
+            for (_, direction, distance) in samples {
+                let offset = *(origin + direction * distance) - *light.position;
+                let on_light = match light.shape {
+                    Shape::Rectangle { normal, right, up } => {
+                        offset.dot(*normal).abs() < 1e-9
+                            && offset.dot(right).abs() <= right.length_square() + 1e-9
+                            && offset.dot(up).abs() <= up.length_square() + 1e-9
+                    }
+                    Shape::Disk { normal, radius } => {
+                        offset.dot(*normal).abs() < 1e-9 && offset.length() <= radius + 1e-9
+                    }
+                    // just in front of the surface
+                    Shape::Sphere { radius } => (radius..radius + 2e-3).contains(&offset.length()),
+                };
+                assert!(on_light, "{:?} at {:?}", light.shape, offset);
+            }
+        }
+    }
+
+    #[test]
+    fn weights_sum_to_the_solid_angle_over_the_area() {
+        // seen from the origin, straight below the center at distance 1
+        let expected = [
+            // solid angle 4 atan(ab / (h sqrt(a² + b² + h²))) over the area 4ab
+            4.0 * (0.5 / 2.25f64.sqrt()).atan() / 2.0,
+            // 2 pi (1 - h / sqrt(h² + R²)) over pi R²
+            2.0 * (1.0 - 1.0 / 2f64.sqrt()),
+            // 2 pi (1 - cos_max) over the projected area pi r²
+            2.0 * (1.0 - 0.75f64.sqrt()) / 0.25,
+        ];
+        let origin = Position::new(Vec3::ZERO);
+        for (light, expected) in lights().into_iter().zip(expected) {
+            let samples = light.sample(origin, 4096, &mut Rng::new(2));
+            let sum = samples.iter().map(|(color, _, _)| color.r).sum::<f64>();
+            assert!(
+                (sum - expected).abs() < 0.005 * expected,
+                "{:?}: {} instead of {}",
+                light.shape,
+                sum,
+                expected
+            );
+
+            // and from afar the light is as bright as a point light
+            let far = Position::new(Vec3::new(0.0, -99.0, 0.0));
+            let sum = light
+                .sample(far, 64, &mut Rng::new(3))
+                .iter()
+                .map(|(color, _, _)| color.r)
+                .sum::<f64>();
+            let point = light.test(far).unwrap().0.r;
+            assert!((sum - point).abs() < 1e-3 * point, "{:?}", light.shape);
+        }
+    }
+
+    #[test]
+    fn half_blocked_light_casts_a_penumbra() {
+        // a wall a quarter of the way up to the lights covering x < 0
+        let lit = |light: &AreaLight, x: f64| {
+            let position = Position::new(Vec3::new(x, 0.0, 0.0));
+            let samples = light.sample(position, 1024, &mut Rng::new(4));
+            let total = samples.iter().map(|(color, _, _)| color.r).sum::<f64>();
+            let visible = samples
+                .iter()
+                .filter(|(_, direction, _)| x + direction.x * 0.25 / direction.y >= 0.0)
+                .map(|(color, _, _)| color.r)
+                .sum::<f64>();
+            visible / total
+        };
+        for light in lights() {
+            assert_eq!(lit(&light, -2.0), 0.0, "{:?}", light.shape);
+            assert_eq!(lit(&light, 2.0), 1.0, "{:?}", light.shape);
+            // half by symmetry, and brighter towards the lit side
+            assert!((lit(&light, 0.0) - 0.5).abs() < 0.01, "{:?}", light.shape);
+            let penumbra = [-0.2, -0.1, 0.0, 0.1, 0.2].map(|x| lit(&light, x));
+            assert!(
+                penumbra.windows(2).all(|pair| pair[0] < pair[1]),
+                "{:?}",
+                light.shape
+            );
+            assert!(penumbra[0] > 0.0 && penumbra[4] < 1.0, "{:?}", light.shape);
+        }
+    }
+}
@@ -1,13 +1,19 @@
-use core::types::rt::Light;
+use area::{AreaLight, Shape};
+use core::types::{
+    math::{Direction, Vec3},
+    rt::Light,
+};
 use directional::DirectionalLight;
 use jsonc::Value;
 use point::PointLight;
 use spot::SpotLight;
+use std::collections::HashMap;
 
 use crate::{
     angle_from_json_value, direction_from_json_value, hdr_color_from_json_value,
     position_from_json_value,
 };
+pub mod area;
 pub mod directional;
 pub mod point;
 pub mod spot;
@@ -32,23 +38,8 @@ pub fn from_json_value(json: &Value) -> Result<Box<dyn Light + Send + Sync>, Str
                 .ok_or("Missing required field: position")?;
             let position = position_from_json_value(position_json)?;
 
-            // XXX: validation?
-            let range = if let Some(Value::Number(r)) = dict.get("range") {
-                if *r <= 0.0 {
-                    return Err("range must be greater than 0".to_string());
-                }
-                *r
-            } else {
-                f64::INFINITY
-            };
-
-            // XXX: validation?
-            let attenuation = if let Some(Value::Bool(a)) = dict.get("attenuation") {
-                *a
-            } else {
-                true
-            };
-            Box::new(PointLight::new(color, position, range, attenuation))
+            let (range, falloff) = range_and_falloff_from_json_value(dict)?;
+            Box::new(PointLight::new(color, position, range, falloff))
         }
         "directional" => {
             let color_json = dict.get("color").ok_or("Missing required field: color")?;
@@ -72,37 +63,181 @@ pub fn from_json_value(json: &Value) -> Result<Box<dyn Light + Send + Sync>, Str
 
             let angle_json = dict.get("angle").ok_or("Missing required field: angle")?;
             let angle = angle_from_json_value(angle_json)?;
+            if angle <= 0.0 || angle >= std::f64::consts::PI {
+                return Err("angle must be between 0 and 180 degrees".to_string());
+            }
+
+            let inner_angle = match dict.get("innerAngle") {
+                Some(json) => angle_from_json_value(json)?,
+                None => angle,
+            };
+            if inner_angle < 0.0 || inner_angle > angle {
+                return Err("innerAngle must be between 0 and angle".to_string());
+            }
 
             let direction_json = dict
                 .get("direction")
                 .ok_or("Missing required field: direction")?;
             let direction = direction_from_json_value(direction_json)?;
 
-            let range = if let Some(Value::Number(r)) = dict.get("range") {
-                if *r <= 0.0 {
-                    return Err("range must be greater than 0".to_string());
-                }
-                *r
-            } else {
-                f64::INFINITY
-            };
-
-            let attenuation = if let Some(Value::Bool(a)) = dict.get("attenuation") {
-                *a
-            } else {
-                true
-            };
+            let (range, falloff) = range_and_falloff_from_json_value(dict)?;
             Box::new(SpotLight::new(
                 color,
                 position,
                 angle,
+                inner_angle,
                 direction,
                 range,
-                attenuation,
+                falloff,
             ))
         }
+        "area" => {
+            let color_json = dict.get("color").ok_or("Missing required field: color")?;
+            let color = hdr_color_from_json_value(color_json)?;
+
+            let position_json = dict
+                .get("position")
+                .ok_or("Missing required field: position")?;
+            let position = position_from_json_value(position_json)?;
+
+            let Value::String(shape_str) =
+                dict.get("shape").ok_or("Missing required field: shape")?
+            else {
+                return Err("shape must be a string".to_string());
+            };
+            let shape = match shape_str.as_str() {
+                "rectangle" => {
+                    let normal = area_normal_from_json_value(dict)?;
+                    let Some(Value::Array(size)) = dict.get("size") else {
+                        return Err("Missing required field: size".to_string());
+                    };
+                    let [Value::Number(width), Value::Number(height)] = size.as_slice() else {
+                        return Err("size must be an array of 2 numbers".to_string());
+                    };
+                    if *width <= 0.0 || *height <= 0.0 {
+                        return Err("size must be greater than 0".to_string());
+                    }
+
+                    // keep the width horizontal unless the light faces straight up or down
+                    let up_hint = if normal.y.abs() > 0.999 {
+                        Vec3::new(0.0, 0.0, 1.0)
+                    } else {
+                        Vec3::new(0.0, 1.0, 0.0)
+                    };
+                    let right = Direction::new(up_hint.cross(*normal));
+                    let up = Direction::new(normal.cross(*right));
+                    Shape::Rectangle {
+                        normal,
+                        right: *right * (width / 2.0),
+                        up: *up * (height / 2.0),
+                    }
+                }
+                "disk" => Shape::Disk {
+                    normal: area_normal_from_json_value(dict)?,
+                    radius: area_radius_from_json_value(dict)?,
+                },
+                "sphere" => Shape::Sphere {
+                    radius: area_radius_from_json_value(dict)?,
+                },
+                _ => return Err(format!("Unknown area light shape: {}", shape_str)),
+            };
+
+            Box::new(AreaLight::new(color, position, shape))
+        }
         _ => return Err(format!("Unknown light type: {}", type_str)),
     };
 
     Ok(light)
 }
+
+/// How light intensity decreases with distance.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Falloff {
+    /// Constant intensity.
+    None,
+    /// `1 / d`
+    Linear,
+    /// `1 / d²`, physically correct for point-like emitters.
+    InverseSquare,
+    /// Inverse square multiplied by a window that smoothly reaches zero at the range.
+    Smooth,
+}
+
+impl Falloff {
+    /// Intensity multiplier at `distance`, lights are culled beyond `range` by the caller.
+    pub fn factor(self, distance: f64, range: f64) -> f64 {
+        // don't blow up for points (almost) on the light
+        let distance = distance.max(1e-3);
+        match self {
+            Falloff::None => 1.0,
+            Falloff::Linear => 1.0 / distance,
+            Falloff::InverseSquare => 1.0 / (distance * distance),
+            Falloff::Smooth => {
+                let window = (1.0 - (distance / range).powi(4)).clamp(0.0, 1.0);
+                window * window / (distance * distance)
+            }
+        }
+    }
+}
+
+pub fn falloff_from_str(s: &str) -> Result<Falloff, String> {
+    match s {
+        "none" => Ok(Falloff::None),
+        "linear" => Ok(Falloff::Linear),
+        "inverseSquare" => Ok(Falloff::InverseSquare),
+        "smooth" => Ok(Falloff::Smooth),
+        _ => Err(format!("Unknown falloff: {}", s)),
+    }
+}
+
+/// Parses the `range`, `attenuation` and `falloff` fields shared by point and spot lights.
+fn range_and_falloff_from_json_value(
+    dict: &HashMap<String, Value>,
+) -> Result<(f64, Falloff), String> {
+    let range = match dict.get("range") {
+        Some(Value::Number(r)) if *r > 0.0 => *r,
+        Some(_) => return Err("range must be a number greater than 0".to_string()),
+        None => f64::INFINITY,
+    };
+
+    let attenuation = match dict.get("attenuation") {
+        Some(Value::Bool(a)) => *a,
+        Some(_) => return Err("attenuation must be a boolean".to_string()),
+        None => true,
+    };
+
+    let falloff = match dict.get("falloff") {
+        Some(Value::String(s)) => Some(falloff_from_str(s)?),
+        Some(_) => return Err("falloff must be a string".to_string()),
+        None => None,
+    };
+
+    let falloff = match (attenuation, falloff) {
+        (true, falloff) => falloff.unwrap_or(Falloff::InverseSquare),
+        (false, None | Some(Falloff::None)) => Falloff::None,
+        (false, Some(_)) => {
+            return Err("falloff can't be used with attenuation disabled".to_string())
+        }
+    };
+    if falloff == Falloff::Smooth && range.is_infinite() {
+        return Err("smooth falloff requires a range".to_string());
+    }
+
+    Ok((range, falloff))
+}
+
+/// Parses the `direction` an area light faces.
+fn area_normal_from_json_value(dict: &HashMap<String, Value>) -> Result<Direction, String> {
+    let direction_json = dict
+        .get("direction")
+        .ok_or("Missing required field: direction")?;
+    direction_from_json_value(direction_json)
+}
+
+fn area_radius_from_json_value(dict: &HashMap<String, Value>) -> Result<f64, String> {
+    match dict.get("radius") {
+        Some(Value::Number(r)) if *r > 0.0 => Ok(*r),
+        Some(_) => Err("radius must be a number greater than 0".to_string()),
+        None => Err("Missing required field: radius".to_string()),
+    }
+}
@@ -4,12 +4,14 @@ use core::types::{
 };
 use types::HDRColor;
 
+use super::Falloff;
+
 #[derive(Clone, Debug)]
 pub struct PointLight {
     position: Position,
     color: HDRColor,
     range: f64,
-    attenuation: bool,
+    falloff: Falloff,
 }
 
 impl Light for PointLight {
@@ -17,25 +19,23 @@ impl Light for PointLight {
         // Compute the vector from the ray's origin to the light's position
         let to_light = self.position - position;
         let (direction, distance) = to_light.direction_and_length();
-        if distance < 1e-3 {
-            return Some((self.color, direction, distance));
+        if distance > self.range {
+            return None;
         }
 
-        // Compute attenuation using inverse square falloff
-        let attenuation_factor = 1.0 / (distance * distance);
-        let attenuated_color = self.color * attenuation_factor;
+        let attenuated_color = self.color * self.falloff.factor(distance, self.range);
 
         Some((attenuated_color, direction, distance))
     }
 }
 
 impl PointLight {
-    pub fn new(color: HDRColor, position: Position, range: f64, attenuation: bool) -> Self {
+    pub fn new(color: HDRColor, position: Position, range: f64, falloff: Falloff) -> Self {
         PointLight {
             color,
             position,
             range,
-            attenuation,
+            falloff,
         }
     }
 }
@@ -4,14 +4,19 @@ use core::types::{
 };
 use types::HDRColor;
 
+use super::Falloff;
+
 #[derive(Clone, Debug)]
 pub struct SpotLight {
     color: HDRColor,
     position: Position,
+    /// Full aperture of the cone.
     angle: f64,
+    /// Full aperture of the fully lit core, the falloff happens between this and `angle`.
+    inner_angle: f64,
     direction: Direction,
     range: f64,
-    attenuation: bool,
+    falloff: Falloff,
 }
 
 impl SpotLight {
@@ -19,24 +24,49 @@ impl SpotLight {
         color: HDRColor,
         position: Position,
         angle: f64,
+        inner_angle: f64,
         direction: Direction,
         range: f64,
-        attenuation: bool,
+        falloff: Falloff,
     ) -> Self {
         SpotLight {
             color,
             position,
             angle,
+            inner_angle,
             direction,
             range,
-            attenuation,
+            falloff,
         }
     }
 }
 
 impl Light for SpotLight {
-    fn test(&self, _position: Position) -> Option<(HDRColor, Direction, f64)> {
-        // TODO: Spot light 구현
-        None
+    fn test(&self, position: Position) -> Option<(HDRColor, Direction, f64)> {
+        let (direction, distance) = (self.position - position).direction_and_length();
+        if distance > self.range {
+            return None;
+        }
+
+        // compare cosines, the angle from the axis grows as they shrink
+        let cos_theta = (-direction).dot(self.direction);
+        let cos_outer = (self.angle / 2.0).cos();
+        let cos_inner = (self.inner_angle / 2.0).cos();
+        let cone = if cos_theta >= cos_inner {
+            1.0
+        } else if cos_theta <= cos_outer {
+            return None;
+        } else {
+            let t = (cos_theta - cos_outer) / (cos_inner - cos_outer);
+            t * t * (3.0 - 2.0 * t)
+        };
+
+        let attenuation_factor = self.falloff.factor(distance, self.range);
+
+        Some((
+            self.color * (cone * attenuation_factor),
+            direction,
+            distance,
+        ))
     }
 }
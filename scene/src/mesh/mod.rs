@@ -0,0 +1,111 @@
+use std::{collections::HashMap, path::Path};
+
+use core::types::math::Vec3;
+use types::{HDRColor, LDRColor};
+
+use crate::MeshLoader;
+
+pub mod obj;
+pub mod ply;
+pub mod stl;
+
+/// Triangles as loaded from a mesh file, before they become a primitive.
+#[derive(Clone, Debug, Default)]
+pub struct MeshData {
+    pub positions: Vec<Vec3>,
+    pub normals: Vec<Vec3>,
+    /// Texture coordinates with v growing downward, like image rows.
+    pub uvs: Vec<(f64, f64)>,
+    /// Colors of the positions, empty if the file has none. They replace
+    /// the material's albedo.
+    pub colors: Vec<LDRColor>,
+    pub triangles: Vec<MeshTriangle>,
+    pub materials: Vec<MeshMaterial>,
+}
+
+/// Indices into the attributes of a [`MeshData`], counterclockwise seen
+/// from the front.
+#[derive(Clone, Debug)]
+pub struct MeshTriangle {
+    pub positions: [usize; 3],
+    pub normals: Option<[usize; 3]>,
+    pub uvs: Option<[usize; 3]>,
+    /// Index into `materials`, None for the primitive's own material.
+    pub material: Option<usize>,
+}
+
+/// Material of a mesh file, with the texture still a path relative to the scene.
+#[derive(Clone, Debug)]
+pub struct MeshMaterial {
+    pub name: String,
+    pub albedo: LDRColor,
+    pub albedo_texture: Option<String>,
+    pub roughness: f64,
+    pub metallic: f64,
+    pub transmission: f64,
+    pub ior: f64,
+    pub emission: HDRColor,
+}
+
+impl MeshMaterial {
+    pub fn new(name: String) -> Self {
+        MeshMaterial {
+            name,
+            albedo: LDRColor::new(1.0, 1.0, 1.0),
+            albedo_texture: None,
+            roughness: 0.0,
+            metallic: 0.0,
+            transmission: 0.0,
+            ior: 1.5,
+            emission: HDRColor::BLACK,
+        }
+    }
+}
+
+/// Parses a mesh file in the format its extension names: `.obj` (reading
+/// its material libraries through `load`), `.ply` or binary `.stl`.
+pub fn from_bytes(
+    path: &str,
+    bytes: &[u8],
+    mut load: impl FnMut(&str) -> Result<Vec<u8>, String>,
+) -> Result<MeshData, String> {
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("obj") => obj::parse(&text(path, bytes)?, path, |path| text(path, &load(path)?)),
+        Some("ply") => ply::parse(bytes, path),
+        Some("stl") => stl::parse(bytes, path),
+        _ => Err(format!("Unknown mesh format: {}", path)),
+    }
+}
+
+fn text(path: &str, bytes: &[u8]) -> Result<String, String> {
+    String::from_utf8(bytes.to_vec()).map_err(|_| format!("{} is not UTF-8 text", path))
+}
+
+/// Mesh files kept in memory, such as the entries of a `pack` archive.
+pub struct MemoryMeshLoader {
+    files: HashMap<String, Vec<u8>>,
+}
+
+impl MemoryMeshLoader {
+    pub fn new(files: HashMap<String, Vec<u8>>) -> Self {
+        MemoryMeshLoader { files }
+    }
+
+    fn file(&self, path: &str) -> Result<Vec<u8>, String> {
+        // paths are joined with the platform's separator, archives use slashes
+        self.files
+            .get(&path.replace('\\', "/"))
+            .cloned()
+            .ok_or(format!("No such file: {}", path))
+    }
+}
+
+impl MeshLoader for MemoryMeshLoader {
+    fn load(&self, path: &str) -> Result<MeshData, String> {
+        from_bytes(path, &self.file(path)?, |path| self.file(path))
+    }
+}
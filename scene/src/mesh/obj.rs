@@ -0,0 +1,248 @@
+use std::{collections::HashMap, path::Path};
+
+use core::types::math::Vec3;
+use types::{HDRColor, LDRColor};
+
+use super::{MeshData, MeshMaterial, MeshTriangle};
+
+/// Parse a Wavefront OBJ file at `path`, reading the MTL libraries it names
+/// through `load`. Paths given to `load` and texture paths are relative to
+/// the same directory as `path`.
+pub fn parse(
+    source: &str,
+    path: &str,
+    mut load: impl FnMut(&str) -> Result<String, String>,
+) -> Result<MeshData, String> {
+    let mut mesh = MeshData::default();
+    let mut material_indices: HashMap<String, usize> = HashMap::new();
+    let mut material = None;
+
+    for (number, line) in source.lines().enumerate() {
+        let error = |message: &str| format!("{}:{}: {}", path, number + 1, message);
+        let line = line.split('#').next().unwrap_or("");
+        let mut words = line.split_whitespace();
+        let Some(keyword) = words.next() else {
+            continue;
+        };
+        let arguments: Vec<&str> = words.collect();
+        match keyword {
+            "v" => mesh
+                .positions
+                .push(vec3(&arguments).ok_or_else(|| error("bad vertex"))?),
+            "vn" => mesh
+                .normals
+                .push(vec3(&arguments).ok_or_else(|| error("bad normal"))?),
+            "vt" => {
+                let u = number_at(&arguments, 0).ok_or_else(|| error("bad texture coordinate"))?;
+                let v = number_at(&arguments, 1).unwrap_or(0.0);
+                // OBJ puts v = 0 at the bottom of the image
+                mesh.uvs.push((u, 1.0 - v));
+            }
+            "f" => {
+                let corners = arguments
+                    .iter()
+                    .map(|corner| face_corner(corner, &mesh))
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or_else(|| error("bad face"))?;
+                if corners.len() < 3 {
+                    return Err(error("faces need at least 3 vertices"));
+                }
+                // polygons become fans around their first corner
+                for i in 1..corners.len() - 1 {
+                    let triangle = [corners[0], corners[i], corners[i + 1]];
+                    let all = |attribute: fn(&Corner) -> Option<usize>| {
+                        Some([
+                            attribute(&triangle[0])?,
+                            attribute(&triangle[1])?,
+                            attribute(&triangle[2])?,
+                        ])
+                    };
+                    mesh.triangles.push(MeshTriangle {
+                        positions: triangle.map(|corner| corner.position),
+                        normals: all(|corner| corner.normal),
+                        uvs: all(|corner| corner.uv),
+                        material,
+                    });
+                }
+            }
+            "mtllib" => {
+                for library in arguments {
+                    let library = sibling(path, library);
+                    let source = load(&library)?;
+                    for parsed in parse_mtl(&source, &library)? {
+                        material_indices.insert(parsed.name.clone(), mesh.materials.len());
+                        mesh.materials.push(parsed);
+                    }
+                }
+            }
+            "usemtl" => {
+                let name = arguments
+                    .first()
+                    .ok_or_else(|| error("missing material name"))?;
+                material = Some(
+                    *material_indices
+                        .get(*name)
+                        .ok_or_else(|| error(&format!("unknown material {}", name)))?,
+                );
+            }
+            // groups, objects and smoothing groups don't matter for rendering
+            _ => {}
+        }
+    }
+
+    Ok(mesh)
+}
+
+#[derive(Clone, Copy)]
+struct Corner {
+    position: usize,
+    uv: Option<usize>,
+    normal: Option<usize>,
+}
+
+/// A `v`, `v/vt`, `v//vn` or `v/vt/vn` face corner, with 1-based or
+/// negative, relative indices.
+fn face_corner(corner: &str, mesh: &MeshData) -> Option<Corner> {
+    let mut parts = corner.split('/');
+    let index = |part: Option<&str>, count: usize| -> Option<Option<usize>> {
+        match part {
+            None | Some("") => Some(None),
+            Some(part) => {
+                let index: i64 = part.parse().ok()?;
+                let index = if index < 0 {
+                    count as i64 + index
+                } else {
+                    index - 1
+                };
+                (0..count as i64)
+                    .contains(&index)
+                    .then_some(Some(index as usize))
+            }
+        }
+    };
+    let position = index(parts.next(), mesh.positions.len())??;
+    let uv = index(parts.next(), mesh.uvs.len())?;
+    let normal = index(parts.next(), mesh.normals.len())?;
+    Some(Corner {
+        position,
+        uv,
+        normal,
+    })
+}
+
+fn parse_mtl(source: &str, path: &str) -> Result<Vec<MeshMaterial>, String> {
+    let mut materials: Vec<MeshMaterial> = Vec::new();
+    // the PBR extension's roughness wins over the Blinn-Phong exponent
+    let mut has_roughness = false;
+    for (number, line) in source.lines().enumerate() {
+        let error = |message: &str| format!("{}:{}: {}", path, number + 1, message);
+        let line = line.split('#').next().unwrap_or("");
+        let mut words = line.split_whitespace();
+        let Some(keyword) = words.next() else {
+            continue;
+        };
+        let arguments: Vec<&str> = words.collect();
+        if keyword == "newmtl" {
+            let name = arguments
+                .first()
+                .ok_or_else(|| error("missing material name"))?;
+            materials.push(MeshMaterial::new(name.to_string()));
+            has_roughness = false;
+            continue;
+        }
+        let Some(material) = materials.last_mut() else {
+            continue;
+        };
+        let number = || number_at(&arguments, 0).ok_or_else(|| error("bad number"));
+        match keyword {
+            "Kd" => {
+                let color = vec3(&arguments).ok_or_else(|| error("bad color"))?;
+                material.albedo = LDRColor::new(color.x, color.y, color.z);
+            }
+            "Ke" => {
+                let color = vec3(&arguments).ok_or_else(|| error("bad color"))?;
+                material.emission = HDRColor {
+                    r: color.x,
+                    g: color.y,
+                    b: color.z,
+                };
+            }
+            "Ns" if !has_roughness => {
+                material.roughness = (2.0 / (number()?.max(0.0) + 2.0)).sqrt();
+            }
+            "Pr" => {
+                material.roughness = number()?.clamp(0.0, 1.0);
+                has_roughness = true;
+            }
+            "Pm" => material.metallic = number()?.clamp(0.0, 1.0),
+            "Ni" => material.ior = number()?.max(1.0),
+            "d" => material.transmission = (1.0 - number()?).clamp(0.0, 1.0),
+            "Tr" => material.transmission = number()?.clamp(0.0, 1.0),
+            // options may come before the file name, which is last
+            "map_Kd" => {
+                let file = arguments
+                    .last()
+                    .ok_or_else(|| error("missing texture path"))?;
+                material.albedo_texture = Some(sibling(path, file));
+            }
+            _ => {}
+        }
+    }
+    Ok(materials)
+}
+
+fn number_at(arguments: &[&str], index: usize) -> Option<f64> {
+    arguments.get(index)?.parse().ok()
+}
+
+fn vec3(arguments: &[&str]) -> Option<Vec3> {
+    Some(Vec3::new(
+        number_at(arguments, 0)?,
+        number_at(arguments, 1)?,
+        number_at(arguments, 2)?,
+    ))
+}
+
+/// `name` in the directory of the file at `path`.
+fn sibling(path: &str, name: &str) -> String {
+    match Path::new(path).parent() {
+        Some(directory) => directory.join(name).to_string_lossy().into_owned(),
+        None => name.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_faces_and_materials() {
+        let source = "mtllib box.mtl\n\
+            v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n\
+            vt 0 0\nvt 1 1\n\
+            vn 0 0 1\n\
+            f 1 2 3 4\n\
+            usemtl red\n\
+            f -4/1/1 -3/2/1 -2/1/1 # relative\n";
+        let mesh = parse(source, "models/box.obj", |path| {
+            assert_eq!(path, Path::new("models/box.mtl").to_string_lossy());
+            Ok("newmtl red\nKd 1 0 0\nNs 0\nmap_Kd -bm 1 red.bmp\n".to_string())
+        })
+        .unwrap();
+
+        assert_eq!(mesh.triangles.len(), 3);
+        assert_eq!(mesh.triangles[1].positions, [0, 2, 3]);
+        assert!(mesh.triangles[1].normals.is_none());
+        assert_eq!(mesh.triangles[2].uvs, Some([0, 1, 0]));
+        assert_eq!(mesh.triangles[2].material, Some(0));
+        assert_eq!(mesh.uvs[1], (1.0, 0.0));
+
+        let red = &mesh.materials[0];
+        assert_eq!(red.albedo.r, 1.0);
+        assert_eq!(red.roughness, 1.0);
+        assert_eq!(
+            red.albedo_texture.as_deref(),
+            Some(Path::new("models/red.bmp").to_string_lossy().as_ref())
+        );
+    }
+}
@@ -0,0 +1,366 @@
+use core::types::math::Vec3;
+use types::LDRColor;
+
+use super::{MeshData, MeshTriangle};
+
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    Ascii,
+    LittleEndian,
+    BigEndian,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Type {
+    Int8,
+    UInt8,
+    Int16,
+    UInt16,
+    Int32,
+    UInt32,
+    Float32,
+    Float64,
+}
+
+impl Type {
+    fn from_str(s: &str) -> Option<Type> {
+        match s {
+            "char" | "int8" => Some(Type::Int8),
+            "uchar" | "uint8" => Some(Type::UInt8),
+            "short" | "int16" => Some(Type::Int16),
+            "ushort" | "uint16" => Some(Type::UInt16),
+            "int" | "int32" => Some(Type::Int32),
+            "uint" | "uint32" => Some(Type::UInt32),
+            "float" | "float32" => Some(Type::Float32),
+            "double" | "float64" => Some(Type::Float64),
+            _ => None,
+        }
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Type::Int8 | Type::UInt8 => 1,
+            Type::Int16 | Type::UInt16 => 2,
+            Type::Int32 | Type::UInt32 | Type::Float32 => 4,
+            Type::Float64 => 8,
+        }
+    }
+
+    /// Scale mapping the type's range of color values to 0..1.
+    fn color_scale(self) -> f64 {
+        match self {
+            Type::UInt8 => 1.0 / 255.0,
+            Type::UInt16 => 1.0 / 65535.0,
+            _ => 1.0,
+        }
+    }
+}
+
+struct Property {
+    name: String,
+    /// The type of the count for lists.
+    list: Option<Type>,
+    value: Type,
+}
+
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+/// Values of the body, in order, whatever the format.
+struct Reader<'a> {
+    format: Format,
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl Reader<'_> {
+    fn read(&mut self, value: Type) -> Option<f64> {
+        if self.format == Format::Ascii {
+            let rest = &self.bytes[self.offset..];
+            let start = rest.iter().position(|b| !b.is_ascii_whitespace())?;
+            let length = rest[start..]
+                .iter()
+                .position(|b| b.is_ascii_whitespace())
+                .unwrap_or(rest.len() - start);
+            self.offset += start + length;
+            return std::str::from_utf8(&rest[start..start + length])
+                .ok()?
+                .parse()
+                .ok();
+        }
+
+        let bytes = self.bytes.get(self.offset..self.offset + value.size())?;
+        self.offset += value.size();
+        let mut buffer = [0; 8];
+        buffer[..bytes.len()].copy_from_slice(bytes);
+        if self.format == Format::BigEndian {
+            buffer[..bytes.len()].reverse();
+        }
+        let [b0, b1, b2, b3, ..] = buffer;
+        Some(match value {
+            Type::Int8 => b0 as i8 as f64,
+            Type::UInt8 => b0 as f64,
+            Type::Int16 => i16::from_le_bytes([b0, b1]) as f64,
+            Type::UInt16 => u16::from_le_bytes([b0, b1]) as f64,
+            Type::Int32 => i32::from_le_bytes([b0, b1, b2, b3]) as f64,
+            Type::UInt32 => u32::from_le_bytes([b0, b1, b2, b3]) as f64,
+            Type::Float32 => f32::from_le_bytes([b0, b1, b2, b3]) as f64,
+            Type::Float64 => f64::from_le_bytes(buffer),
+        })
+    }
+}
+
+/// Parse an ASCII or binary PLY file with a `vertex` element (`x`, `y`, `z`,
+/// optionally `nx`..., `u`/`v` or `s`/`t`, and `red`, `green`, `blue`) and a
+/// `face` element of vertex index lists. Other elements are skipped.
+pub fn parse(bytes: &[u8], path: &str) -> Result<MeshData, String> {
+    let error = |message: &str| format!("{}: {}", path, message);
+
+    let (elements, format, body) = header(bytes).map_err(|message| error(&message))?;
+    let mut reader = Reader {
+        format,
+        bytes: &bytes[body..],
+        offset: 0,
+    };
+    let mut mesh = MeshData::default();
+
+    for element in elements.iter() {
+        let index = |name: &str| {
+            element
+                .properties
+                .iter()
+                .position(|property| property.name == name)
+        };
+        let any = |names: &[&str]| names.iter().find_map(|name| index(name));
+        let position = [index("x"), index("y"), index("z")];
+        let normal = [index("nx"), index("ny"), index("nz")];
+        let uv = [
+            any(&["u", "s", "texture_u", "texture_s"]),
+            any(&["v", "t", "texture_v", "texture_t"]),
+        ];
+        let color = [index("red"), index("green"), index("blue")];
+        let indices = any(&["vertex_indices", "vertex_index"]);
+        if element.name == "vertex" && position.iter().any(Option::is_none) {
+            return Err(error("vertices need x, y and z"));
+        }
+        if element.name == "face" && indices.is_none() {
+            return Err(error("faces need vertex_indices"));
+        }
+
+        for _ in 0..element.count {
+            let mut values = Vec::with_capacity(element.properties.len());
+            let mut list = Vec::new();
+            for (i, property) in element.properties.iter().enumerate() {
+                let read_error = || error(&format!("bad {} {}", element.name, property.name));
+                let Some(count_type) = property.list else {
+                    values.push(reader.read(property.value).ok_or_else(read_error)?);
+                    continue;
+                };
+                let count = reader.read(count_type).ok_or_else(read_error)?;
+                let count = as_index(count).ok_or_else(|| error("invalid vertex index"))?;
+                let items = (0..count)
+                    .map(|_| reader.read(property.value))
+                    .collect::<Option<Vec<f64>>>()
+                    .ok_or_else(read_error)?;
+                values.push(0.0);
+                if Some(i) == indices {
+                    list = items;
+                }
+            }
+
+            let get = |index: Option<usize>| index.map(|index| values[index]);
+            match element.name.as_str() {
+                "vertex" => {
+                    let [x, y, z] = position.map(|index| values[index.unwrap()]);
+                    mesh.positions.push(Vec3::new(x, y, z));
+                    if let [Some(x), Some(y), Some(z)] = normal.map(get) {
+                        mesh.normals.push(Vec3::new(x, y, z));
+                    }
+                    if let [Some(u), Some(v)] = uv.map(get) {
+                        // v = 0 is at the bottom like in OBJ
+                        mesh.uvs.push((u, 1.0 - v));
+                    }
+                    if let [Some(r), Some(g), Some(b)] = color.map(get) {
+                        let scale = element.properties[color[0].unwrap()].value.color_scale();
+                        mesh.colors
+                            .push(LDRColor::new(r * scale, g * scale, b * scale));
+                    }
+                }
+                "face" => {
+                    let list = list
+                        .iter()
+                        .map(|index| as_index(*index))
+                        .collect::<Option<Vec<_>>>()
+                        .ok_or_else(|| error("invalid vertex index"))?;
+                    if list.len() < 3 {
+                        return Err(error("faces need at least 3 vertices"));
+                    }
+                    for i in 1..list.len() - 1 {
+                        mesh.triangles.push(MeshTriangle {
+                            positions: [list[0], list[i], list[i + 1]],
+                            normals: None,
+                            uvs: None,
+                            material: None,
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // attributes are per vertex, so share the position indices
+    let count = mesh.positions.len();
+    for triangle in mesh.triangles.iter_mut() {
+        if triangle.positions.iter().any(|index| *index >= count) {
+            return Err(error("face vertex index out of range"));
+        }
+        if mesh.normals.len() == count {
+            triangle.normals = Some(triangle.positions);
+        }
+        if mesh.uvs.len() == count {
+            triangle.uvs = Some(triangle.positions);
+        }
+    }
+    Ok(mesh)
+}
+
+/// The elements, the format and the offset of the body.
+/// List counts and indices are stored as numbers of any type, but only
+/// non-negative integers make sense.
+fn as_index(value: f64) -> Option<usize> {
+    (value >= 0.0 && value.fract() == 0.0).then_some(value as usize)
+}
+
+fn header(bytes: &[u8]) -> Result<(Vec<Element>, Format, usize), String> {
+    let mut elements: Vec<Element> = Vec::new();
+    let mut format = None;
+    let mut offset = 0;
+
+    let mut lines = bytes.split(|b| *b == b'\n');
+    let first = lines.next().unwrap_or_default();
+    if first.trim_ascii_end() != b"ply" {
+        return Err("not a PLY file".to_string());
+    }
+    offset += first.len() + 1;
+    for line in lines {
+        offset += line.len() + 1;
+        let line = std::str::from_utf8(line).map_err(|_| "bad header".to_string())?;
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["end_header"] => {
+                let format = format.ok_or("missing format")?;
+                return Ok((elements, format, offset));
+            }
+            ["format", name, _version] => {
+                format = Some(match *name {
+                    "ascii" => Format::Ascii,
+                    "binary_little_endian" => Format::LittleEndian,
+                    "binary_big_endian" => Format::BigEndian,
+                    _ => return Err(format!("unknown format {}", name)),
+                })
+            }
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count.parse().map_err(|_| "bad element count")?,
+                properties: Vec::new(),
+            }),
+            ["property", "list", count, value, name] => {
+                let element = elements.last_mut().ok_or("property before element")?;
+                element.properties.push(Property {
+                    name: name.to_string(),
+                    list: Some(Type::from_str(count).ok_or("unknown property type")?),
+                    value: Type::from_str(value).ok_or("unknown property type")?,
+                });
+            }
+            ["property", value, name] => {
+                let element = elements.last_mut().ok_or("property before element")?;
+                element.properties.push(Property {
+                    name: name.to_string(),
+                    list: None,
+                    value: Type::from_str(value).ok_or("unknown property type")?,
+                });
+            }
+            ["comment", ..] | ["obj_info", ..] | [] => {}
+            _ => return Err(format!("bad header line: {}", line)),
+        }
+    }
+    Err("missing end_header".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = "ply\n\
+        format {} 1.0\n\
+        comment made by hand\n\
+        element vertex 4\n\
+        property float x\nproperty float y\nproperty float z\n\
+        property uchar red\nproperty uchar green\nproperty uchar blue\n\
+        element face 1\n\
+        property list uchar int vertex_indices\n\
+        end_header\n";
+
+    fn check(mesh: MeshData) {
+        assert_eq!(mesh.positions.len(), 4);
+        assert_eq!(mesh.positions[2].y, 1.0);
+        assert_eq!(mesh.colors[1].r, 1.0);
+        assert_eq!(mesh.colors[1].g, 0.0);
+        assert_eq!(mesh.triangles.len(), 2);
+        assert_eq!(mesh.triangles[1].positions, [0, 2, 3]);
+    }
+
+    #[test]
+    fn parses_ascii_and_binary() {
+        let vertices = [
+            ([0.0f32, 0.0, 0.0], [255u8, 255, 255]),
+            ([1.0, 0.0, 0.0], [255, 0, 0]),
+            ([1.0, 1.0, 0.0], [0, 255, 0]),
+            ([0.0, 1.0, 0.0], [0, 0, 255]),
+        ];
+
+        let mut ascii = HEADER.replace("{}", "ascii");
+        for (position, color) in vertices {
+            ascii += &format!(
+                "{} {} {} {} {} {}\n",
+                position[0], position[1], position[2], color[0], color[1], color[2]
+            );
+        }
+        ascii += "4 0 1 2 3\n";
+        check(parse(ascii.as_bytes(), "quad.ply").unwrap());
+
+        let mut binary = HEADER.replace("{}", "binary_big_endian").into_bytes();
+        for (position, color) in vertices {
+            for value in position {
+                binary.extend(value.to_be_bytes());
+            }
+            binary.extend(color);
+        }
+        binary.push(4);
+        for index in 0..4i32 {
+            binary.extend(index.to_be_bytes());
+        }
+        check(parse(&binary, "quad.ply").unwrap());
+
+        for face in [
+            "4 0 1 2 -3\n",
+            "4 0 1 2 2.5\n",
+            "4 0 1 2 nan\n",
+            "-4 0 1 2\n",
+            "3.5 0 1 2\n",
+        ] {
+            let ascii = ascii.replace("4 0 1 2 3\n", face);
+            let error = parse(ascii.as_bytes(), "quad.ply").unwrap_err();
+            assert!(
+                error.contains("invalid vertex index"),
+                "{}: {}",
+                face,
+                error
+            );
+        }
+    }
+}
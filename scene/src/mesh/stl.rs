@@ -0,0 +1,74 @@
+use std::collections::HashMap;
+
+use core::types::math::Vec3;
+
+use super::{MeshData, MeshTriangle};
+
+const HEADER_SIZE: usize = 84;
+const TRIANGLE_SIZE: usize = 50;
+
+/// Parse a binary STL file. Corners at the same position are merged so
+/// the mesh can be smooth shaded.
+pub fn parse(bytes: &[u8], path: &str) -> Result<MeshData, String> {
+    if bytes.len() < HEADER_SIZE {
+        return Err(format!("{}: too short for a binary STL file", path));
+    }
+    let count = u32::from_le_bytes(bytes[80..84].try_into().unwrap()) as usize;
+    if bytes.len() != HEADER_SIZE + count * TRIANGLE_SIZE {
+        return Err(if bytes.starts_with(b"solid") {
+            format!("{}: ASCII STL is not supported", path)
+        } else {
+            format!("{}: size doesn't match the triangle count", path)
+        });
+    }
+
+    let mut mesh = MeshData::default();
+    let mut indices: HashMap<[u32; 3], usize> = HashMap::new();
+    for triangle in bytes[HEADER_SIZE..].chunks_exact(TRIANGLE_SIZE) {
+        // the facet normal comes first, the winding tells the same
+        let corner = |i: usize| -> [u32; 3] {
+            let offset = 12 + i * 12;
+            [0, 4, 8].map(|j| {
+                u32::from_le_bytes(triangle[offset + j..offset + j + 4].try_into().unwrap())
+            })
+        };
+        let positions = [0, 1, 2].map(|i| {
+            let bits = corner(i);
+            *indices.entry(bits).or_insert_with(|| {
+                let [x, y, z] = bits.map(|bits| f32::from_bits(bits) as f64);
+                mesh.positions.push(Vec3::new(x, y, z));
+                mesh.positions.len() - 1
+            })
+        });
+        mesh.triangles.push(MeshTriangle {
+            positions,
+            normals: None,
+            uvs: None,
+            material: None,
+        });
+    }
+    Ok(mesh)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merges_shared_corners() {
+        let mut bytes = vec![0; 80];
+        bytes.extend(2u32.to_le_bytes());
+        for corners in [[[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]; 2] {
+            bytes.extend([0u8; 12]);
+            for value in corners.iter().flatten() {
+                bytes.extend((*value as f32).to_le_bytes());
+            }
+            bytes.extend([0u8; 2]);
+        }
+
+        let mesh = parse(&bytes, "two.stl").unwrap();
+        assert_eq!(mesh.positions.len(), 3);
+        assert_eq!(mesh.triangles[1].positions, [0, 1, 2]);
+        assert!(parse(b"solid ascii", "ascii.stl").is_err());
+    }
+}
@@ -0,0 +1,186 @@
+use std::collections::HashMap;
+
+use core::types::{
+    math::{Aabb, Position, Vec3},
+    rt::{Hit, Ray},
+};
+use jsonc::Value;
+
+use crate::{
+    object::material_from_json_value, position_from_json_value, texture::TexturePoint, AssetCache,
+    ImageLoader,
+};
+
+use super::{
+    util::{azimuth, convex_hits, inside_quadratic, positive_number},
+    Material, RTObject,
+};
+
+/// Points within `radius` of the segment of length `height` along the y
+/// axis, centered on `position`.
+struct Capsule {
+    position: Position,
+    radius: f64,
+    height: f64,
+    material: Material,
+}
+
+impl Capsule {
+    /// Outward normal at a point of the surface, relative to the center.
+    fn normal(&self, point: Vec3) -> Vec3 {
+        let half = self.height / 2.0;
+        point - Vec3::new(0.0, point.y.clamp(-half, half), 0.0)
+    }
+
+    /// Entry and exit distances along the ray with the outward normals there.
+    fn interval(&self, ray: Ray) -> Option<((f64, Vec3), (f64, Vec3))> {
+        let o = *(ray.origin - self.position);
+        let d = *ray.direction;
+        let half = self.height / 2.0;
+        let r2 = self.radius * self.radius;
+
+        // the cylinder between the end centers and the two end spheres are
+        // convex and so is their union, one range covers all of them
+        let mut pieces = Vec::with_capacity(3);
+        let [side, _] = inside_quadratic(
+            d.x * d.x + d.z * d.z,
+            2.0 * (o.x * d.x + o.z * d.z),
+            o.x * o.x + o.z * o.z - r2,
+        )
+        .unwrap_or([None, None]);
+        if let Some((t1, t2)) = side {
+            let (c1, c2) = if d.y.abs() < 1e-12 {
+                if o.y.abs() <= half {
+                    (f64::NEG_INFINITY, f64::INFINITY)
+                } else {
+                    (f64::INFINITY, f64::NEG_INFINITY)
+                }
+            } else {
+                let c1 = (-half - o.y) / d.y;
+                let c2 = (half - o.y) / d.y;
+                (c1.min(c2), c1.max(c2))
+            };
+            pieces.push((t1.max(c1), t2.min(c2)));
+        }
+        for y in [-half, half] {
+            let center = o - Vec3::new(0.0, y, 0.0);
+            let [sphere, _] =
+                inside_quadratic(d.dot(d), 2.0 * center.dot(d), center.dot(center) - r2)
+                    .unwrap_or([None, None]);
+            pieces.extend(sphere);
+        }
+
+        let (entry, exit) = pieces
+            .into_iter()
+            .filter(|(t1, t2)| t1 <= t2)
+            .reduce(|(a1, a2), (b1, b2)| (a1.min(b1), a2.max(b2)))?;
+        Some((
+            (entry, self.normal(o + d * entry)),
+            (exit, self.normal(o + d * exit)),
+        ))
+    }
+
+    /// The texture wraps around the axis and spans the whole length.
+    fn texture_point(&self, position: Position) -> TexturePoint {
+        let local = *(position - self.position);
+        let length = self.height + 2.0 * self.radius;
+        TexturePoint {
+            u: azimuth(local),
+            v: 0.5 - local.y / length,
+            object: local,
+            world: *position,
+            footprint: [(0.0, 0.0); 2],
+        }
+    }
+}
+
+impl RTObject for Capsule {
+    fn aabb(&self) -> Option<Aabb> {
+        let extent = Vec3::new(self.radius, self.height / 2.0 + self.radius, self.radius);
+        Some(Aabb::new(*self.position - extent, *self.position + extent))
+    }
+
+    fn test(&self, ray: Ray) -> Vec<Hit> {
+        let Some((entry, exit)) = self.interval(ray) else {
+            return Vec::new();
+        };
+        convex_hits(&self.material, ray, entry, exit, |position, _| {
+            self.texture_point(position)
+        })
+    }
+
+    fn occluded(&self, ray: Ray, max_distance: f64) -> bool {
+        self.interval(ray).is_some_and(|((t1, _), (t2, _))| {
+            (t1 > 0.0 && t1 < max_distance) || (t2 > 0.0 && t2 < max_distance)
+        })
+    }
+}
+
+pub fn from_json_value(
+    dict: &HashMap<String, Value>,
+    assets: &mut AssetCache<impl ImageLoader>,
+) -> Result<Box<dyn RTObject + Send + Sync>, String> {
+    let radius = positive_number(dict, "radius")?;
+    let Value::Number(height) = dict.get("height").ok_or("Missing required field: height")? else {
+        return Err("height must be a number".to_string());
+    };
+    if *height < 0.0 {
+        return Err("height must not be negative".to_string());
+    }
+    let position = dict
+        .get("position")
+        .map(position_from_json_value)
+        .unwrap_or(Ok(Position::new(Vec3::ZERO)))?;
+    let material = material_from_json_value(dict.get("material"), assets)?;
+    Ok(Box::new(Capsule {
+        position,
+        radius,
+        height: *height,
+        material,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::util::test::{assert_hits, ray};
+    use super::*;
+
+    fn capsule(height: f64) -> Capsule {
+        Capsule {
+            position: Position::new(Vec3::ZERO),
+            radius: 1.0,
+            height,
+            material: Material::default(),
+        }
+    }
+
+    fn check(capsule: &Capsule, origin: Vec3, direction: Vec3, distances: &[f64]) {
+        let ray = ray(origin, direction);
+        assert_hits(&capsule.test(ray), distances, ray);
+    }
+
+    #[test]
+    fn hits() {
+        // the segment runs from y = -1 to 1
+        let capsule = capsule(2.0);
+        check(&capsule, Vec3::new(0.0, 5.0, 0.0), -Vec3::Y, &[3.0, 7.0]);
+        // through an end sphere, the side, and the other end sphere
+        check(&capsule, Vec3::new(0.6, 5.0, 0.0), -Vec3::Y, &[3.2, 6.8]);
+        // across the side and across an end sphere
+        check(&capsule, Vec3::new(-5.0, 0.5, 0.6), Vec3::X, &[4.2, 5.8]);
+        check(&capsule, Vec3::new(-5.0, 1.6, 0.0), Vec3::X, &[4.2, 5.8]);
+        check(&capsule, Vec3::new(-5.0, 2.5, 0.0), Vec3::X, &[]);
+        // from inside the side and inside an end sphere
+        check(&capsule, Vec3::new(0.0, 0.0, 0.0), Vec3::X, &[0.0, 1.0]);
+        check(&capsule, Vec3::new(0.0, 1.5, 0.0), Vec3::Y, &[0.0, 0.5]);
+        check(&capsule, Vec3::new(0.0, 1.5, 0.0), -Vec3::Y, &[0.0, 3.5]);
+    }
+
+    #[test]
+    fn zero_height_is_a_sphere() {
+        let sphere = capsule(0.0);
+        check(&sphere, Vec3::new(-5.0, 0.0, 0.0), Vec3::X, &[4.0, 6.0]);
+        check(&sphere, Vec3::new(0.0, 5.0, 0.0), -Vec3::Y, &[4.0, 6.0]);
+        check(&sphere, Vec3::new(0.0, 0.0, 0.0), Vec3::Y, &[0.0, 1.0]);
+    }
+}
@@ -0,0 +1,261 @@
+use std::collections::HashMap;
+
+use core::types::{
+    math::{Aabb, Position, Vec3},
+    rt::{Hit, Ray},
+};
+use jsonc::Value;
+
+use crate::{
+    object::material_from_json_value, position_from_json_value, texture::TexturePoint, AssetCache,
+    ImageLoader,
+};
+
+use super::{
+    util::{azimuth, convex_hits, inside_quadratic, positive_number},
+    Material, RTObject,
+};
+
+/// Capped cone along the y axis, centered on `position`, truncated when
+/// both radii are positive. Cylinders have equal radii.
+struct Cone {
+    position: Position,
+    bottom_radius: f64,
+    top_radius: f64,
+    height: f64,
+    material: Material,
+}
+
+impl Cone {
+    /// Change of radius per unit of height.
+    fn slope(&self) -> f64 {
+        (self.top_radius - self.bottom_radius) / self.height
+    }
+
+    fn radius_at(&self, y: f64) -> f64 {
+        self.bottom_radius + self.slope() * (y + self.height / 2.0)
+    }
+
+    fn side_normal(&self, point: Vec3) -> Vec3 {
+        let normal = Vec3::new(point.x, -self.slope() * self.radius_at(point.y), point.z);
+        if normal.length_square() > 1e-18 {
+            normal
+        } else {
+            // the apex
+            Vec3::Y * -self.slope().signum()
+        }
+    }
+
+    /// Entry and exit distances along the ray with the outward normals there.
+    fn interval(&self, ray: Ray) -> Option<((f64, Vec3), (f64, Vec3))> {
+        let o = *(ray.origin - self.position);
+        let d = *ray.direction;
+        let half = self.height / 2.0;
+
+        // between the caps
+        let caps = if d.y.abs() < 1e-12 {
+            if o.y.abs() > half {
+                return None;
+            }
+            (f64::NEG_INFINITY, f64::INFINITY)
+        } else {
+            let t1 = (-half - o.y) / d.y;
+            let t2 = (half - o.y) / d.y;
+            (t1.min(t2), t1.max(t2))
+        };
+
+        // inside the side, where x² + z² - r(y)² <= 0 with r(y) = k y + m
+        let k = self.slope();
+        let m = self.radius_at(0.0);
+        let a = d.x * d.x + d.z * d.z - k * k * d.y * d.y;
+        let b = 2.0 * (o.x * d.x + o.z * d.z - k * d.y * (k * o.y + m));
+        let c = o.x * o.x + o.z * o.z - (k * o.y + m).powi(2);
+        let sides = inside_quadratic(a, b, c)?;
+
+        // the solid is convex, so only one piece of the side meets the caps
+        sides.into_iter().flatten().find_map(|(t1, t2)| {
+            // whichever bound is tighter is the surface the ray crosses
+            let enters_side = t1 >= caps.0;
+            let exits_side = t2 <= caps.1;
+            let entry = if enters_side { t1 } else { caps.0 };
+            let exit = if exits_side { t2 } else { caps.1 };
+            // a ray through the apex of a pointed cone touches one nappe
+            // before crossing the other
+            if entry >= exit {
+                return None;
+            }
+            let entry_normal = if enters_side {
+                self.side_normal(o + d * entry)
+            } else {
+                Vec3::Y * -d.y.signum()
+            };
+            let exit_normal = if exits_side {
+                self.side_normal(o + d * exit)
+            } else {
+                Vec3::Y * d.y.signum()
+            };
+            Some(((entry, entry_normal), (exit, exit_normal)))
+        })
+    }
+
+    /// The side spans the whole texture, caps are projected from above.
+    fn texture_point(&self, position: Position, normal: Vec3) -> TexturePoint {
+        let local = *(position - self.position);
+        let (u, v) = if normal.x == 0.0 && normal.z == 0.0 {
+            let diameter = 2.0 * self.bottom_radius.max(self.top_radius);
+            (local.x / diameter + 0.5, local.z / diameter + 0.5)
+        } else {
+            (azimuth(local), 0.5 - local.y / self.height)
+        };
+        TexturePoint {
+            u,
+            v,
+            object: local,
+            world: *position,
+            footprint: [(0.0, 0.0); 2],
+        }
+    }
+}
+
+impl RTObject for Cone {
+    fn aabb(&self) -> Option<Aabb> {
+        let radius = self.bottom_radius.max(self.top_radius);
+        let extent = Vec3::new(radius, self.height / 2.0, radius);
+        Some(Aabb::new(*self.position - extent, *self.position + extent))
+    }
+
+    fn test(&self, ray: Ray) -> Vec<Hit> {
+        let Some((entry, exit)) = self.interval(ray) else {
+            return Vec::new();
+        };
+        convex_hits(&self.material, ray, entry, exit, |position, normal| {
+            self.texture_point(position, normal)
+        })
+    }
+
+    fn occluded(&self, ray: Ray, max_distance: f64) -> bool {
+        self.interval(ray).is_some_and(|((t1, _), (t2, _))| {
+            (t1 > 0.0 && t1 < max_distance) || (t2 > 0.0 && t2 < max_distance)
+        })
+    }
+}
+
+pub fn cylinder_from_json_value(
+    dict: &HashMap<String, Value>,
+    assets: &mut AssetCache<impl ImageLoader>,
+) -> Result<Box<dyn RTObject + Send + Sync>, String> {
+    let radius = positive_number(dict, "radius")?;
+    let height = positive_number(dict, "height")?;
+    let position = dict
+        .get("position")
+        .map(position_from_json_value)
+        .unwrap_or(Ok(Position::new(Vec3::ZERO)))?;
+    let material = material_from_json_value(dict.get("material"), assets)?;
+    Ok(Box::new(Cone {
+        position,
+        bottom_radius: radius,
+        top_radius: radius,
+        height,
+        material,
+    }))
+}
+
+pub fn from_json_value(
+    dict: &HashMap<String, Value>,
+    assets: &mut AssetCache<impl ImageLoader>,
+) -> Result<Box<dyn RTObject + Send + Sync>, String> {
+    let radius = positive_number(dict, "radius")?;
+    let Value::Number(top_radius) = dict.get("topRadius").unwrap_or(&Value::Number(0.0)) else {
+        return Err("topRadius must be a number".to_string());
+    };
+    if *top_radius < 0.0 {
+        return Err("topRadius must not be negative".to_string());
+    }
+    let height = positive_number(dict, "height")?;
+    let position = dict
+        .get("position")
+        .map(position_from_json_value)
+        .unwrap_or(Ok(Position::new(Vec3::ZERO)))?;
+    let material = material_from_json_value(dict.get("material"), assets)?;
+    Ok(Box::new(Cone {
+        position,
+        bottom_radius: radius,
+        top_radius: *top_radius,
+        height,
+        material,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::util::test::{assert_hits, ray};
+    use super::*;
+
+    fn cone(bottom_radius: f64, top_radius: f64) -> Cone {
+        Cone {
+            position: Position::new(Vec3::ZERO),
+            bottom_radius,
+            top_radius,
+            height: 2.0,
+            material: Material::default(),
+        }
+    }
+
+    fn check(cone: &Cone, origin: Vec3, direction: Vec3, distances: &[f64]) {
+        let ray = ray(origin, direction);
+        assert_hits(&cone.test(ray), distances, ray);
+    }
+
+    #[test]
+    fn cylinder_hits() {
+        let cylinder = cone(1.0, 1.0);
+        // along the axis, through both caps
+        check(&cylinder, Vec3::new(0.0, 5.0, 0.0), -Vec3::Y, &[4.0, 6.0]);
+        // across, off the axis
+        let half_chord = (1.0f64 - 0.3 * 0.3).sqrt();
+        check(
+            &cylinder,
+            Vec3::new(-5.0, 0.5, 0.3),
+            Vec3::X,
+            &[5.0 - half_chord, 5.0 + half_chord],
+        );
+        check(&cylinder, Vec3::new(0.0, 0.5, 0.0), Vec3::X, &[0.0, 1.0]);
+        check(&cylinder, Vec3::new(-5.0, 1.5, 0.0), Vec3::X, &[]);
+    }
+
+    #[test]
+    fn truncated_cone_hits() {
+        // radius 2 at y = -1 down to 1 at y = 1
+        let cone = cone(2.0, 1.0);
+        check(&cone, Vec3::new(0.0, 5.0, 0.0), -Vec3::Y, &[4.0, 6.0]);
+        check(&cone, Vec3::new(-5.0, 0.0, 0.0), Vec3::X, &[3.5, 6.5]);
+        check(&cone, Vec3::new(0.0, 0.0, 0.0), Vec3::X, &[0.0, 1.5]);
+
+        // in through the top cap at (0.5, 1) and out the side at (1.5, 0)
+        let diagonal = Vec3::new(1.0, -1.0, 0.0);
+        let root2 = 2.0f64.sqrt();
+        check(
+            &cone,
+            Vec3::new(0.0, 1.5, 0.0),
+            diagonal,
+            &[0.5 * root2, 1.5 * root2],
+        );
+        // and back, in through the side and out the cap
+        check(
+            &cone,
+            Vec3::new(3.0, -1.5, 0.0),
+            -diagonal,
+            &[1.5 * root2, 2.5 * root2],
+        );
+    }
+
+    #[test]
+    fn pointed_cone_hits() {
+        let cone = cone(1.0, 0.0);
+        // in at the apex, out through the base
+        check(&cone, Vec3::new(0.0, 5.0, 0.0), -Vec3::Y, &[4.0, 6.0]);
+        check(&cone, Vec3::new(0.0, -5.0, 0.0), Vec3::Y, &[4.0, 6.0]);
+        check(&cone, Vec3::new(-5.0, 0.0, 0.0), Vec3::X, &[4.5, 5.5]);
+        check(&cone, Vec3::new(0.0, -0.5, 0.0), Vec3::Z, &[0.0, 0.75]);
+    }
+}
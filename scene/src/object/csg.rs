@@ -1,10 +1,13 @@
 use jsonc::Value;
 
-use crate::{ImageCache, ImageLoader};
+use crate::{AssetCache, ImageLoader};
 
 use super::RTObject;
 
-use core::types::rt::{Hit, Ray};
+use core::types::{
+    math::Aabb,
+    rt::{Hit, Ray},
+};
 use std::collections::HashMap;
 
 fn remove_duplicate_hits(sorted: &mut Vec<Hit>) {
@@ -29,6 +32,20 @@ struct Union {
 }
 
 impl RTObject for Union {
+    fn occluded(&self, ray: Ray, max_distance: f64) -> bool {
+        // the boundary is made of parts of the children's surfaces, so only
+        // build the full hit list if one of them is crossed
+        (self.a.occluded(ray, max_distance) || self.b.occluded(ray, max_distance))
+            && self
+                .test(ray)
+                .iter()
+                .any(|hit| hit.is_occluding(max_distance))
+    }
+
+    fn aabb(&self) -> Option<Aabb> {
+        Some(self.a.aabb()?.union(self.b.aabb()?))
+    }
+
     fn test(&self, ray: Ray) -> Vec<Hit> {
         let mut a_hits = self.a.test(ray);
         let mut b_hits = self.b.test(ray);
@@ -73,6 +90,23 @@ struct Intersection {
 }
 
 impl RTObject for Intersection {
+    fn occluded(&self, ray: Ray, max_distance: f64) -> bool {
+        // the boundary is made of parts of the children's surfaces, so only
+        // build the full hit list if one of them is crossed
+        (self.a.occluded(ray, max_distance) || self.b.occluded(ray, max_distance))
+            && self
+                .test(ray)
+                .iter()
+                .any(|hit| hit.is_occluding(max_distance))
+    }
+
+    fn aabb(&self) -> Option<Aabb> {
+        match (self.a.aabb(), self.b.aabb()) {
+            (Some(a), Some(b)) => Some(a.intersection(b)),
+            (a, b) => a.or(b),
+        }
+    }
+
     fn test(&self, ray: Ray) -> Vec<Hit> {
         let mut a_hits = self.a.test(ray);
         if a_hits.is_empty() {
@@ -117,6 +151,20 @@ struct Difference {
 }
 
 impl RTObject for Difference {
+    fn occluded(&self, ray: Ray, max_distance: f64) -> bool {
+        // the boundary is made of parts of the children's surfaces, so only
+        // build the full hit list if one of them is crossed
+        (self.a.occluded(ray, max_distance) || self.b.occluded(ray, max_distance))
+            && self
+                .test(ray)
+                .iter()
+                .any(|hit| hit.is_occluding(max_distance))
+    }
+
+    fn aabb(&self) -> Option<Aabb> {
+        self.a.aabb()
+    }
+
     fn test(&self, ray: Ray) -> Vec<Hit> {
         let mut a_hits = self.a.test(ray);
         if a_hits.is_empty() {
@@ -169,16 +217,12 @@ impl RTObject for Difference {
 pub fn from_json_value(
     dict: &HashMap<String, Value>,
     type_str: &String,
-    image_cache: &ImageCache<impl ImageLoader>,
+    assets: &mut AssetCache<impl ImageLoader>,
 ) -> Result<Box<dyn RTObject + Send + Sync>, String> {
-    let a = crate::object::from_json_value(
-        dict.get("a").ok_or("Missing required field: a")?,
-        image_cache,
-    )?;
-    let b = crate::object::from_json_value(
-        dict.get("b").ok_or("Missing required field: b")?,
-        image_cache,
-    )?;
+    let a =
+        crate::object::from_json_value(dict.get("a").ok_or("Missing required field: a")?, assets)?;
+    let b =
+        crate::object::from_json_value(dict.get("b").ok_or("Missing required field: b")?, assets)?;
 
     match type_str.as_str() {
         "union" => Ok(Box::new(Union { a, b })),
@@ -1,31 +1,27 @@
 use crate::{
-    object::material_from_json_value, position_from_json_value, scale_from_json_value, ImageCache,
-    ImageLoader,
+    object::material_from_json_value, position_from_json_value, scale_from_json_value,
+    texture::TexturePoint, AssetCache, ImageLoader,
 };
 
-use super::RTObject;
+use super::{util::box_uv, Material, RTObject};
 
 use core::types::{
-    math::{Direction, Position, Vec3},
+    math::{Aabb, Direction, Position, Vec3},
     rt::{Hit, Ray},
 };
 use jsonc::Value;
 use std::collections::HashMap;
-use types::LDRColor;
 
 #[derive(Clone, Debug)]
 pub struct Cube {
     position: Position,
-    albedo: LDRColor,
     scale: Vec3,
-    roughness: f64,
-    metallic: f64,
+    material: Material,
 }
 
-impl RTObject for Cube {
-    fn test(&self, ray: Ray) -> Vec<Hit> {
-        let mut result = Vec::new();
-
+impl Cube {
+    /// Entry and exit distances along the ray with the normals of the faces
+    fn slabs(&self, ray: Ray) -> Option<(f64, Vec3, f64, Vec3)> {
         let min = Position::new(Vec3::new(
             self.position.x - self.scale.x / 2.0,
             self.position.y - self.scale.y / 2.0,
@@ -86,13 +82,50 @@ impl RTObject for Cube {
                     normal_max = normal2;
                 }
                 if t_min > t_max {
-                    return result;
+                    return None;
                 }
             } else if *o < *min || *o > *max {
-                return result;
+                return None;
             }
         }
 
+        Some((t_min, normal_min, t_max, normal_max))
+    }
+
+    /// Each face spans the whole texture.
+    fn texture_point(&self, position: Position, normal: Vec3) -> TexturePoint {
+        let local = *(position - self.position);
+        let (u, v) = box_uv(
+            Vec3::new(
+                local.x / self.scale.x,
+                local.y / self.scale.y,
+                local.z / self.scale.z,
+            ),
+            normal,
+        );
+        TexturePoint {
+            u: u + 0.5,
+            v: v + 0.5,
+            object: local,
+            world: *position,
+            footprint: [(0.0, 0.0); 2],
+        }
+    }
+}
+
+impl RTObject for Cube {
+    fn aabb(&self) -> Option<Aabb> {
+        let extent = self.scale * 0.5;
+        Some(Aabb::new(*self.position - extent, *self.position + extent))
+    }
+
+    fn test(&self, ray: Ray) -> Vec<Hit> {
+        let mut result = Vec::new();
+
+        let Some((mut t_min, normal_min, t_max, normal_max)) = self.slabs(ray) else {
+            return result;
+        };
+
         if t_min < 0.0 && t_max < 0.0 {
             return result;
         }
@@ -103,34 +136,38 @@ impl RTObject for Cube {
 
         if t_min <= t_max {
             if t_min >= 0.0 {
-                result.push(Hit {
-                    normal: Direction::new(normal_min),
-                    distance: t_min,
-                    is_front_face: true,
-                    albedo: self.albedo,
-                    roughness: self.roughness,
-                    metallic: self.metallic,
-                });
+                result.push(self.material.hit_at(
+                    ray,
+                    t_min,
+                    Direction::new(normal_min),
+                    true,
+                    |position| self.texture_point(position, normal_min),
+                ));
             }
             if t_max >= 0.0 {
-                result.push(Hit {
-                    normal: Direction::new(normal_max),
-                    distance: t_max,
-                    is_front_face: false,
-                    albedo: self.albedo,
-                    roughness: self.roughness,
-                    metallic: self.metallic,
-                });
+                result.push(self.material.hit_at(
+                    ray,
+                    t_max,
+                    Direction::new(normal_max),
+                    false,
+                    |position| self.texture_point(position, normal_max),
+                ));
             }
         }
 
         result
     }
+
+    fn occluded(&self, ray: Ray, max_distance: f64) -> bool {
+        self.slabs(ray).is_some_and(|(t_min, _, t_max, _)| {
+            (t_min > 0.0 && t_min < max_distance) || (t_max > 0.0 && t_max < max_distance)
+        })
+    }
 }
 
 pub fn from_json_value(
     dict: &HashMap<String, Value>,
-    image_cache: &ImageCache<impl ImageLoader>,
+    assets: &mut AssetCache<impl ImageLoader>,
 ) -> Result<Box<dyn RTObject + Send + Sync>, String> {
     let scale = dict
         .get("size")
@@ -140,13 +177,10 @@ pub fn from_json_value(
         .get("position")
         .map(position_from_json_value)
         .unwrap_or(Ok(Position::new(Vec3::ZERO)))?;
-    let (albedo, roughness, metallic) =
-        material_from_json_value(dict.get("material"), image_cache)?;
+    let material = material_from_json_value(dict.get("material"), assets)?;
     Ok(Box::new(Cube {
         position,
         scale,
-        albedo,
-        roughness,
-        metallic,
+        material,
     }))
 }
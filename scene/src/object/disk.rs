@@ -0,0 +1,197 @@
+use std::collections::HashMap;
+
+use core::types::{
+    math::{Aabb, Position, Vec3},
+    rt::{Hit, Ray},
+};
+use jsonc::Value;
+
+use crate::{
+    object::material_from_json_value, position_from_json_value, texture::TexturePoint, AssetCache,
+    ImageLoader,
+};
+
+use super::{
+    util::{azimuth, convex_hits, positive_number},
+    Material, RTObject,
+};
+
+/// Flat shapes are slabs this thick around their plane, so CSG doesn't take
+/// their two sides for touching surfaces and remove them.
+const THICKNESS: f64 = 1e-5;
+
+/// Flat ring in the xz plane around `position`, a disk when the inner
+/// radius is 0.
+struct Disk {
+    position: Position,
+    inner_radius: f64,
+    radius: f64,
+    material: Material,
+}
+
+impl Disk {
+    /// Entry and exit distances through the slab with the outward normals
+    /// there, if the ray crosses it within the ring.
+    fn interval(&self, ray: Ray) -> Option<((f64, Vec3), (f64, Vec3))> {
+        let o = *(ray.origin - self.position);
+        let d = *ray.direction;
+        if d.y.abs() < 1e-12 {
+            return None;
+        }
+        let point = o + d * (-o.y / d.y);
+        let r2 = point.x * point.x + point.z * point.z;
+        if r2 > self.radius * self.radius || r2 < self.inner_radius * self.inner_radius {
+            return None;
+        }
+        let half = THICKNESS / 2.0;
+        let t1 = (-half - o.y) / d.y;
+        let t2 = (half - o.y) / d.y;
+        let normal = Vec3::Y * d.y.signum();
+        Some(((t1.min(t2), -normal), (t1.max(t2), normal)))
+    }
+
+    /// Disks are projected from above, rings are unrolled with v going outward.
+    fn texture_point(&self, position: Position) -> TexturePoint {
+        let local = *(position - self.position);
+        let (u, v) = if self.inner_radius == 0.0 {
+            let diameter = 2.0 * self.radius;
+            (local.x / diameter + 0.5, local.z / diameter + 0.5)
+        } else {
+            let distance = local.x.hypot(local.z);
+            (
+                azimuth(local),
+                (distance - self.inner_radius) / (self.radius - self.inner_radius),
+            )
+        };
+        TexturePoint {
+            u,
+            v,
+            object: local,
+            world: *position,
+            footprint: [(0.0, 0.0); 2],
+        }
+    }
+}
+
+impl RTObject for Disk {
+    fn aabb(&self) -> Option<Aabb> {
+        let extent = Vec3::new(self.radius, THICKNESS / 2.0, self.radius);
+        Some(Aabb::new(*self.position - extent, *self.position + extent))
+    }
+
+    fn test(&self, ray: Ray) -> Vec<Hit> {
+        let Some((entry, exit)) = self.interval(ray) else {
+            return Vec::new();
+        };
+        convex_hits(&self.material, ray, entry, exit, |position, _| {
+            self.texture_point(position)
+        })
+    }
+
+    fn occluded(&self, ray: Ray, max_distance: f64) -> bool {
+        self.interval(ray).is_some_and(|((t1, _), (t2, _))| {
+            (t1 > 0.0 && t1 < max_distance) || (t2 > 0.0 && t2 < max_distance)
+        })
+    }
+}
+
+pub fn from_json_value(
+    dict: &HashMap<String, Value>,
+    assets: &mut AssetCache<impl ImageLoader>,
+) -> Result<Box<dyn RTObject + Send + Sync>, String> {
+    new(dict, 0.0, assets)
+}
+
+pub fn annulus_from_json_value(
+    dict: &HashMap<String, Value>,
+    assets: &mut AssetCache<impl ImageLoader>,
+) -> Result<Box<dyn RTObject + Send + Sync>, String> {
+    let inner_radius = positive_number(dict, "innerRadius")?;
+    new(dict, inner_radius, assets)
+}
+
+fn new(
+    dict: &HashMap<String, Value>,
+    inner_radius: f64,
+    assets: &mut AssetCache<impl ImageLoader>,
+) -> Result<Box<dyn RTObject + Send + Sync>, String> {
+    let radius = positive_number(dict, "radius")?;
+    if inner_radius >= radius {
+        return Err("innerRadius must be less than radius".to_string());
+    }
+    let position = dict
+        .get("position")
+        .map(position_from_json_value)
+        .unwrap_or(Ok(Position::new(Vec3::ZERO)))?;
+    let material = material_from_json_value(dict.get("material"), assets)?;
+    Ok(Box::new(Disk {
+        position,
+        inner_radius,
+        radius,
+        material,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::util::test::{assert_hits, ray};
+    use super::*;
+
+    fn disk(inner_radius: f64) -> Disk {
+        Disk {
+            position: Position::new(Vec3::ZERO),
+            inner_radius,
+            radius: 1.0,
+            material: Material::default(),
+        }
+    }
+
+    fn check(disk: &Disk, origin: Vec3, direction: Vec3, distances: &[f64]) {
+        let ray = ray(origin, direction);
+        assert_hits(&disk.test(ray), distances, ray);
+    }
+
+    #[test]
+    fn hits() {
+        let half = THICKNESS / 2.0;
+        let disk = disk(0.0);
+        check(
+            &disk,
+            Vec3::new(0.0, 5.0, 0.0),
+            -Vec3::Y,
+            &[5.0 - half, 5.0 + half],
+        );
+        check(
+            &disk,
+            Vec3::new(0.5, -5.0, 0.5),
+            Vec3::Y,
+            &[5.0 - half, 5.0 + half],
+        );
+        // slanted, crossing the plane at (0.5, 0, 0)
+        let length = 1.01f64.sqrt();
+        check(
+            &disk,
+            Vec3::new(0.3, 2.0, 0.0),
+            Vec3::new(0.1, -1.0, 0.0),
+            &[(2.0 - half) * length, (2.0 + half) * length],
+        );
+        check(&disk, Vec3::new(1.5, 5.0, 0.0), -Vec3::Y, &[]);
+        check(&disk, Vec3::new(0.0, 1.0, 0.0), Vec3::X, &[]);
+        // from inside the slab
+        check(&disk, Vec3::new(0.0, 0.0, 0.0), Vec3::Y, &[0.0, half]);
+    }
+
+    #[test]
+    fn annulus_hits() {
+        let half = THICKNESS / 2.0;
+        let annulus = disk(0.5);
+        check(&annulus, Vec3::new(0.0, 5.0, 0.0), -Vec3::Y, &[]);
+        check(
+            &annulus,
+            Vec3::new(0.0, 5.0, 0.75),
+            -Vec3::Y,
+            &[5.0 - half, 5.0 + half],
+        );
+        check(&annulus, Vec3::new(-0.75, 0.0, 0.0), -Vec3::Y, &[0.0, half]);
+    }
+}
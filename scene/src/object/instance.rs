@@ -0,0 +1,173 @@
+use std::{collections::HashMap, sync::Arc};
+
+use core::types::{
+    math::{Aabb, Position},
+    rt::{Hit, Light, RTObject, Ray},
+};
+use jsonc::Value;
+
+use crate::{transform_from_json_value, AssetCache, ImageLoader};
+
+use super::{material_from_json_value, transformed, util::box_texture_point, Material};
+
+/// A model from the scene's `definitions`, shared by all its instances.
+pub type Definition = Arc<dyn RTObject + Send + Sync>;
+
+struct Instance {
+    object: Definition,
+    /// Replaces the materials of the whole model. Textures are projected
+    /// onto it as on a box, in the model's space.
+    material: Option<Material>,
+}
+
+impl RTObject for Instance {
+    fn test(&self, ray: Ray) -> Vec<Hit> {
+        let hits = self.object.test(ray);
+        let Some(material) = &self.material else {
+            return hits;
+        };
+        hits.into_iter()
+            .map(|hit| {
+                if !hit.is_occluding(f64::INFINITY) {
+                    // only tells that the ray starts inside, there is no surface to look up
+                    return material.hit(hit.distance, hit.normal, hit.is_front_face);
+                }
+                material.hit_at(
+                    ray,
+                    hit.distance,
+                    hit.normal,
+                    hit.is_front_face,
+                    |position| box_texture_point(*position, Position::default(), *hit.normal),
+                )
+            })
+            .collect()
+    }
+
+    fn occluded(&self, ray: Ray, max_distance: f64) -> bool {
+        self.object.occluded(ray, max_distance)
+    }
+
+    fn aabb(&self) -> Option<Aabb> {
+        self.object.aabb()
+    }
+
+    fn emitter(&self) -> Option<Box<dyn Light + Send + Sync>> {
+        // an overriding material may emit differently, paths still find it by hitting it
+        match self.material {
+            Some(_) => None,
+            None => self.object.emitter(),
+        }
+    }
+}
+
+pub fn from_json_value(
+    dict: &HashMap<String, Value>,
+    definitions: &HashMap<String, Definition>,
+    assets: &mut AssetCache<impl ImageLoader>,
+) -> Result<Box<dyn RTObject + Send + Sync>, String> {
+    let Value::String(name) = dict.get("model").ok_or("Missing required field: model")? else {
+        return Err("instance model must be the name of a definition".to_string());
+    };
+    let object = definitions
+        .get(name)
+        .ok_or(format!("Unknown model: {}", name))?
+        .clone();
+    let material = match dict.get("material") {
+        Some(json) => Some(material_from_json_value(Some(json), assets)?),
+        None => None,
+    };
+
+    let instance = Box::new(Instance { object, material });
+    match dict.get("transform") {
+        Some(json) => Ok(transformed::new(instance, transform_from_json_value(json)?)),
+        None => Ok(instance),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::types::math::Vec3;
+
+    use super::super::util::test::ray;
+    use super::*;
+    use crate::{mesh::MemoryMeshLoader, Image, Scene};
+
+    struct NoImages;
+
+    impl ImageLoader for NoImages {
+        fn load(&self, path: &str) -> Result<Arc<dyn Image + Send + Sync>, String> {
+            Err(format!("No such image: {}", path))
+        }
+    }
+
+    /// Instances of a red unit sphere at the origin, one for each JSON object.
+    fn instances(jsons: &[&str]) -> (Definition, Vec<Box<dyn RTObject + Send + Sync>>) {
+        let mesh_loader = MemoryMeshLoader::new(HashMap::new());
+        let mut assets = AssetCache::new(&NoImages, &mesh_loader);
+        let ball = jsonc::parse(
+            r#"{ "type": "sphere", "radius": 1, "material": { "albedo": [1, 0, 0] } }"#,
+        )
+        .unwrap();
+        let ball: Definition =
+            Arc::from(super::super::from_json_value(&ball, &mut assets).unwrap());
+        let definitions = HashMap::from([("ball".to_string(), ball.clone())]);
+        let instances = jsons
+            .iter()
+            .map(|json| {
+                let Value::Object(dict) = jsonc::parse(json).unwrap() else {
+                    panic!("{} is not an object", json);
+                };
+                from_json_value(&dict, &definitions, &mut assets).unwrap()
+            })
+            .collect();
+        (ball, instances)
+    }
+
+    #[test]
+    fn instances_share_their_model() {
+        let (ball, instances) = instances(&[
+            r#"{ "type": "instance", "model": "ball" }"#,
+            r#"{ "type": "instance", "model": "ball", "transform": [{ "translate": [0, 3, 0] }] }"#,
+        ]);
+        // the definitions map has been dropped, leaving ours and one per instance
+        assert_eq!(Arc::strong_count(&ball), 3);
+
+        let hit = |instance: &dyn RTObject, y: f64| {
+            instance.test(ray(Vec3::new(0.0, y, -5.0), Vec3::new(0.0, 0.0, 1.0)))
+        };
+        assert_eq!(hit(instances[0].as_ref(), 0.0)[0].distance, 4.0);
+        assert_eq!(hit(instances[1].as_ref(), 3.0)[0].distance, 4.0);
+        assert!(hit(instances[1].as_ref(), 0.0).is_empty());
+    }
+
+    #[test]
+    fn material_overrides_the_whole_model() {
+        let (_, instances) = instances(&[
+            r#"{ "type": "instance", "model": "ball" }"#,
+            r#"{ "type": "instance", "model": "ball", "material": { "albedo": [0, 1, 0] } }"#,
+        ]);
+        let albedo = |instance: &dyn RTObject| {
+            let hit = &instance.test(ray(Vec3::new(0.0, 0.0, -5.0), Vec3::new(0.0, 0.0, 1.0)))[0];
+            (hit.albedo.r, hit.albedo.g)
+        };
+        assert_eq!(albedo(instances[0].as_ref()), (1.0, 0.0));
+        assert_eq!(albedo(instances[1].as_ref()), (0.0, 1.0));
+    }
+
+    #[test]
+    fn unknown_models_are_errors() {
+        let json = jsonc::parse(
+            r#"{
+                "imageSize": { "width": 9, "height": 9 },
+                "camera": { "fov": { "max": { "degree": 30 } }, "position": [0, 0, -5], "lookAt": [0, 0, 0] },
+                "voidColor": [0, 0, 0], "ambientLight": [0, 0, 0],
+                "definitions": { "ball": { "type": "sphere", "radius": 1 } },
+                "objects": [{ "type": "instance", "model": "cube" }]
+            }"#,
+        )
+        .unwrap();
+        let mesh_loader = MemoryMeshLoader::new(HashMap::new());
+        let result = Scene::from_json_value(json, &mut AssetCache::new(&NoImages, &mesh_loader));
+        assert_eq!(result.err(), Some("Unknown model: cube".to_string()));
+    }
+}
@@ -0,0 +1,329 @@
+use std::collections::HashMap;
+
+use core::{
+    bvh::Bvh,
+    types::{
+        math::{Aabb, Direction, Position, Vec3},
+        rt::{Hit, Ray},
+    },
+};
+use jsonc::Value;
+use types::LDRColor;
+
+use crate::{
+    mesh::{MeshData, MeshMaterial},
+    object::material_from_json_value,
+    position_from_json_value,
+    texture::{
+        plain::{DeserializablePlainTexture, Filter, Wrap},
+        TexturePoint,
+    },
+    AssetCache, ImageLoader,
+};
+
+use super::{util::box_texture_point, Material, RTObject};
+
+struct Triangle {
+    a: Vec3,
+    ab: Vec3,
+    ac: Vec3,
+    /// Unit normal on the counterclockwise side.
+    normal: Vec3,
+    /// Shading normals at the corners.
+    normals: [Vec3; 3],
+    uvs: Option<[(f64, f64); 3]>,
+    /// Replace the albedo where the material has no texture.
+    colors: Option<[LDRColor; 3]>,
+    material: usize,
+}
+
+impl Triangle {
+    /// Möller–Trumbore: the distance along the ray and the barycentric
+    /// weights of `b` and `c`.
+    fn intersect(&self, ray: Ray) -> Option<(f64, (f64, f64))> {
+        let p = ray.direction.cross(self.ac);
+        let determinant = self.ab.dot(p);
+        if determinant.abs() < 1e-12 {
+            return None; // parallel
+        }
+        let inverse = 1.0 / determinant;
+        let origin = *ray.origin - self.a;
+        let u = origin.dot(p) * inverse;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+        let q = origin.cross(self.ab);
+        let v = q.dot(*ray.direction) * inverse;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+        let t = self.ac.dot(q) * inverse;
+        (t > 0.0).then_some((t, (u, v)))
+    }
+
+    /// Barycentric weights of `b` and `c` for `point` projected on the
+    /// triangle's plane, extrapolated outside of it.
+    fn weights(&self, point: Vec3) -> (f64, f64) {
+        let offset = point - self.a;
+        let (d00, d01, d11) = (
+            self.ab.dot(self.ab),
+            self.ab.dot(self.ac),
+            self.ac.dot(self.ac),
+        );
+        let (d20, d21) = (offset.dot(self.ab), offset.dot(self.ac));
+        let denominator = d00 * d11 - d01 * d01;
+        (
+            (d11 * d20 - d01 * d21) / denominator,
+            (d00 * d21 - d01 * d20) / denominator,
+        )
+    }
+}
+
+/// Triangle soup with its own BVH. Closed meshes are solids whose
+/// counterclockwise side is the outside, so they work in CSG.
+struct Mesh {
+    triangles: Vec<Triangle>,
+    bvh: Bvh,
+    /// The primitive's own material first, then the mesh file's.
+    materials: Vec<Material>,
+    position: Position,
+    closed: bool,
+    aabb: Aabb,
+}
+
+impl Mesh {
+    fn hit(&self, ray: Ray, index: usize, distance: f64, (u, v): (f64, f64)) -> Hit {
+        let triangle = &self.triangles[index];
+        let [n0, n1, n2] = triangle.normals;
+        let shading = n0 * (1.0 - u - v) + n1 * u + n2 * v;
+        // interpolation can cancel out or turn over on badly authored normals
+        let normal = if shading.dot(triangle.normal) > 1e-6 {
+            shading
+        } else {
+            triangle.normal
+        };
+        let is_front_face = triangle.normal.dot(*ray.direction) < 0.0;
+
+        let material = &self.materials[triangle.material];
+        let hit = material.hit_at(
+            ray,
+            distance,
+            Direction::new(normal),
+            is_front_face,
+            |position| self.texture_point(triangle, position),
+        );
+        match triangle.colors {
+            Some([c0, c1, c2]) if material.albedo_texture.is_none() => {
+                let w = 1.0 - u - v;
+                Hit {
+                    albedo: LDRColor::new(
+                        c0.r * w + c1.r * u + c2.r * v,
+                        c0.g * w + c1.g * u + c2.g * v,
+                        c0.b * w + c1.b * u + c2.b * v,
+                    ),
+                    ..hit
+                }
+            }
+            _ => hit,
+        }
+    }
+
+    fn texture_point(&self, triangle: &Triangle, position: Position) -> TexturePoint {
+        let local = *position - *self.position;
+        let Some([uv0, uv1, uv2]) = triangle.uvs else {
+            return box_texture_point(local, self.position, triangle.normal);
+        };
+        let (u, v) = triangle.weights(*position);
+        let w = 1.0 - u - v;
+        TexturePoint {
+            u: uv0.0 * w + uv1.0 * u + uv2.0 * v,
+            v: uv0.1 * w + uv1.1 * u + uv2.1 * v,
+            object: local,
+            world: *position,
+            footprint: [(0.0, 0.0); 2],
+        }
+    }
+}
+
+impl RTObject for Mesh {
+    fn test(&self, ray: Ray) -> Vec<Hit> {
+        if !self.closed {
+            return self
+                .bvh
+                .closest(ray.origin, ray.direction, |index, limit| {
+                    self.triangles[index]
+                        .intersect(ray)
+                        .filter(|(distance, _)| *distance < limit)
+                        .map(|(distance, weights)| (distance, (index, weights)))
+                })
+                .map(|(distance, (index, weights))| self.hit(ray, index, distance, weights))
+                .into_iter()
+                .collect();
+        }
+
+        let mut crossings = Vec::new();
+        self.bvh
+            .all(ray.origin, ray.direction, f64::INFINITY, |index| {
+                if let Some((distance, weights)) = self.triangles[index].intersect(ray) {
+                    let is_front_face = self.triangles[index].normal.dot(*ray.direction) < 0.0;
+                    crossings.push((distance, is_front_face, index, weights));
+                }
+            });
+        crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+        // a ray through an edge or a vertex hits every triangle sharing it
+        crossings.dedup_by(|next, kept| next.1 == kept.1 && (next.0 - kept.0).abs() < 1e-9);
+
+        let mut result = Vec::with_capacity(crossings.len() + 1);
+        if crossings.first().is_some_and(|crossing| !crossing.1) {
+            // the ray starts inside
+            result.push(self.materials[0].hit(0.0, -ray.direction, true));
+        }
+        result.extend(
+            crossings
+                .into_iter()
+                .map(|(distance, _, index, weights)| self.hit(ray, index, distance, weights)),
+        );
+        result
+    }
+
+    fn occluded(&self, ray: Ray, max_distance: f64) -> bool {
+        self.bvh
+            .any(ray.origin, ray.direction, max_distance, |index| {
+                self.triangles[index]
+                    .intersect(ray)
+                    .is_some_and(|(distance, _)| distance < max_distance)
+            })
+    }
+
+    fn aabb(&self) -> Option<Aabb> {
+        Some(self.aabb)
+    }
+}
+
+fn material_from_mesh_material(
+    material: &MeshMaterial,
+    assets: &mut AssetCache<impl ImageLoader>,
+) -> Result<Material, String> {
+    let albedo_texture = match &material.albedo_texture {
+        Some(path) => Some(
+            DeserializablePlainTexture::new(path.clone(), Filter::Trilinear, Wrap::Repeat)
+                .into_texture(assets)?,
+        ),
+        None => None,
+    };
+    Ok(Material {
+        albedo: material.albedo,
+        albedo_texture,
+        roughness: material.roughness,
+        metallic: material.metallic,
+        transmission: material.transmission,
+        ior: material.ior,
+        emission: material.emission,
+    })
+}
+
+/// Area weighted normals of the corners of every position.
+fn vertex_normals(mesh: &MeshData) -> Vec<Vec3> {
+    let mut normals = vec![Vec3::ZERO; mesh.positions.len()];
+    for triangle in mesh.triangles.iter() {
+        let [a, b, c] = triangle.positions.map(|i| mesh.positions[i]);
+        let normal = (b - a).cross(c - a);
+        for i in triangle.positions {
+            normals[i] = normals[i] + normal;
+        }
+    }
+    normals
+}
+
+pub fn from_json_value(
+    dict: &HashMap<String, Value>,
+    assets: &mut AssetCache<impl ImageLoader>,
+) -> Result<Box<dyn RTObject + Send + Sync>, String> {
+    let Value::String(path) = dict.get("path").ok_or("Missing required field: path")? else {
+        return Err("Mesh path must be a string".to_string());
+    };
+    let position = dict
+        .get("position")
+        .map(position_from_json_value)
+        .unwrap_or(Ok(Position::new(Vec3::ZERO)))?;
+    let Value::Bool(closed) = dict.get("closed").unwrap_or(&Value::Bool(false)) else {
+        return Err("closed must be a boolean".to_string());
+    };
+    let Value::Bool(smooth) = dict.get("smooth").unwrap_or(&Value::Bool(true)) else {
+        return Err("smooth must be a boolean".to_string());
+    };
+
+    let mesh = assets.load_mesh(path)?;
+    let mut materials = vec![material_from_json_value(dict.get("material"), assets)?];
+    for material in mesh.materials.iter() {
+        materials.push(material_from_mesh_material(material, assets)?);
+    }
+    let vertex_normals = if *smooth {
+        vertex_normals(&mesh)
+    } else {
+        Vec::new()
+    };
+
+    let mut triangles = Vec::with_capacity(mesh.triangles.len());
+    for triangle in mesh.triangles.iter() {
+        let [a, b, c] = triangle.positions.map(|i| mesh.positions[i] + *position);
+        let (ab, ac) = (b - a, c - a);
+        let normal = ab.cross(ac);
+        if normal.length() == 0.0 {
+            continue; // degenerate, can't be hit
+        }
+        let normal = normal.normalize();
+        let normals = match (*smooth, triangle.normals) {
+            (false, _) => [normal; 3],
+            (true, Some(indices)) => indices.map(|i| mesh.normals[i].normalize()),
+            (true, None) => triangle.positions.map(|i| vertex_normals[i].normalize()),
+        };
+        triangles.push(Triangle {
+            a,
+            ab,
+            ac,
+            normal,
+            normals,
+            uvs: triangle.uvs.map(|indices| indices.map(|i| mesh.uvs[i])),
+            colors: (mesh.colors.len() == mesh.positions.len())
+                .then(|| triangle.positions.map(|i| mesh.colors[i])),
+            material: triangle.material.map_or(0, |index| index + 1),
+        });
+    }
+    if triangles.is_empty() {
+        return Err(format!("Mesh {} has no triangles", path));
+    }
+
+    let bounds: Vec<Option<Aabb>> = triangles
+        .iter()
+        .map(|triangle| {
+            let (b, c) = (triangle.a + triangle.ab, triangle.a + triangle.ac);
+            let min = Vec3::new(
+                triangle.a.x.min(b.x).min(c.x),
+                triangle.a.y.min(b.y).min(c.y),
+                triangle.a.z.min(b.z).min(c.z),
+            );
+            let max = Vec3::new(
+                triangle.a.x.max(b.x).max(c.x),
+                triangle.a.y.max(b.y).max(c.y),
+                triangle.a.z.max(b.z).max(c.z),
+            );
+            Some(Aabb::new(min, max))
+        })
+        .collect();
+    let aabb = bounds
+        .iter()
+        .flatten()
+        .copied()
+        .reduce(Aabb::union)
+        .unwrap();
+
+    Ok(Box::new(Mesh {
+        bvh: Bvh::new(&bounds),
+        triangles,
+        materials,
+        position,
+        closed: *closed,
+        aabb,
+    }))
+}
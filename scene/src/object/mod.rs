@@ -1,21 +1,37 @@
-use core::types::rt::RTObject;
+use std::{fmt, sync::Arc};
+
+use core::types::{
+    math::{Direction, Position, Vec3},
+    rt::{Hit, RTObject, Ray},
+};
 use jsonc::Value;
-use types::LDRColor;
+use types::{HDRColor, LDRColor};
 
-use crate::{ldr_color_from_json_value, ImageCache, ImageLoader};
+use crate::{
+    hdr_color_from_json_value, ldr_color_from_json_value,
+    texture::{Texture, TexturePoint},
+    transform_from_json_value, AssetCache, ImageLoader,
+};
 
+pub mod capsule;
+pub mod cone;
 pub mod csg;
 pub mod cube;
+pub mod disk;
+pub mod instance;
+pub mod mesh;
 pub mod plane;
 pub mod quadratic;
 pub mod quadric;
 pub mod quartic;
 pub mod sphere;
+pub mod torus;
+pub mod transformed;
 pub mod util;
 
 pub fn from_json_value(
     json: &Value,
-    image_cache: &ImageCache<impl ImageLoader>,
+    assets: &mut AssetCache<impl ImageLoader>,
 ) -> Result<Box<dyn RTObject + Send + Sync>, String> {
     let dict = match json {
         Value::Object(dict) => dict,
@@ -27,23 +43,154 @@ pub fn from_json_value(
         _ => return Err("Object must have a 'type' field".to_string()),
     };
 
-    match type_str.as_str() {
-        "union" | "intersection" | "difference" => {
-            csg::from_json_value(dict, type_str, image_cache)
-        }
-        "sphere" => sphere::from_json_value(dict, image_cache),
-        "cube" => cube::from_json_value(dict, image_cache),
-        "plane" => plane::from_json_value(dict, image_cache),
+    let object = match type_str.as_str() {
+        "union" | "intersection" | "difference" => csg::from_json_value(dict, type_str, assets),
+        "sphere" => sphere::from_json_value(dict, assets),
+        "cube" => cube::from_json_value(dict, assets),
+        "plane" => plane::from_json_value(dict, assets),
+        "mesh" => mesh::from_json_value(dict, assets),
+        "cylinder" => cone::cylinder_from_json_value(dict, assets),
+        "cone" => cone::from_json_value(dict, assets),
+        "torus" => torus::from_json_value(dict, assets),
+        "capsule" => capsule::from_json_value(dict, assets),
+        "disk" => disk::from_json_value(dict, assets),
+        "annulus" => disk::annulus_from_json_value(dict, assets),
         _ => return Err(format!("Unknown object type: {}", type_str)),
+    }?;
+
+    match dict.get("transform") {
+        Some(json) => Ok(transformed::new(object, transform_from_json_value(json)?)),
+        None => Ok(object),
+    }
+}
+
+#[derive(Clone)]
+pub struct Material {
+    pub albedo: LDRColor,
+    /// Replaces `albedo` where the surface has UV coordinates.
+    pub albedo_texture: Option<Arc<dyn Texture + Send + Sync>>,
+    pub roughness: f64,
+    pub metallic: f64,
+    pub transmission: f64,
+    pub ior: f64,
+    /// Emitted radiance, color times strength.
+    pub emission: HDRColor,
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Material {
+            albedo: LDRColor::new(1.0, 1.0, 1.0),
+            albedo_texture: None,
+            roughness: 0.0,
+            metallic: 0.0,
+            transmission: 0.0,
+            ior: 1.5,
+            emission: HDRColor::BLACK,
+        }
+    }
+}
+
+impl Material {
+    pub fn hit(&self, distance: f64, normal: Direction, is_front_face: bool) -> Hit {
+        Hit {
+            distance,
+            normal,
+            albedo: self.albedo,
+            is_front_face,
+            roughness: self.roughness,
+            metallic: self.metallic,
+            transmission: self.transmission,
+            ior: self.ior,
+            emission: self.emission,
+        }
+    }
+
+    /// Like [`Material::hit`] for the ray hitting at `distance`, looking the
+    /// albedo up in the texture if there is one. `point` maps positions near
+    /// the hit to texture points.
+    pub fn hit_at(
+        &self,
+        ray: Ray,
+        distance: f64,
+        normal: Direction,
+        is_front_face: bool,
+        point: impl Fn(Position) -> TexturePoint,
+    ) -> Hit {
+        let hit = self.hit(distance, normal, is_front_face);
+        match &self.albedo_texture {
+            Some(texture) => Hit {
+                albedo: texture.get(&texture_point(ray, distance, normal, point)),
+                ..hit
+            },
+            None => hit,
+        }
+    }
+}
+
+/// The texture point where the ray hits, with the UV footprint of its cone.
+fn texture_point(
+    ray: Ray,
+    distance: f64,
+    normal: Direction,
+    point: impl Fn(Position) -> TexturePoint,
+) -> TexturePoint {
+    let position = ray.origin + ray.direction * distance;
+    let center = point(position);
+    let width = ray.cone.width_at(distance);
+    if width <= 0.0 {
+        return center;
+    }
+
+    // the cone's cross-section stretches along the ray's direction over the surface
+    let across = normal.cross(*ray.direction);
+    let (across, along) = if across.length() > 1e-9 {
+        let across = across.normalize();
+        (across, normal.cross(across))
+    } else {
+        normal.tangent_frame()
+    };
+    let cos = normal.dot(ray.direction).abs().max(1e-2);
+
+    let axis = |offset: Vec3| {
+        let forward = point(Position::new(*position + offset));
+        let backward = point(Position::new(*position - offset));
+        let forward = (forward.u - center.u, forward.v - center.v);
+        let backward = (center.u - backward.u, center.v - backward.v);
+        // at most one side crosses a seam of the mapping, where UVs jump
+        if forward.0.hypot(forward.1) <= backward.0.hypot(backward.1) {
+            forward
+        } else {
+            backward
+        }
+    };
+
+    TexturePoint {
+        footprint: [axis(across * width), axis(along * (width / cos))],
+        ..center
+    }
+}
+
+impl fmt::Debug for Material {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Material")
+            .field("albedo", &self.albedo)
+            .field("albedo_texture", &self.albedo_texture.is_some())
+            .field("roughness", &self.roughness)
+            .field("metallic", &self.metallic)
+            .field("transmission", &self.transmission)
+            .field("ior", &self.ior)
+            .field("emission", &self.emission)
+            .finish()
     }
 }
 
 pub fn material_from_json_value(
     json: Option<&Value>,
-    _image_cache: &ImageCache<impl ImageLoader>,
-) -> Result<(LDRColor, f64, f64), String> {
+    assets: &mut AssetCache<impl ImageLoader>,
+) -> Result<Material, String> {
     let Some(json) = json else {
-        return Ok((LDRColor::new(1.0, 1.0, 1.0), 0.0, 0.0));
+        return Ok(Material::default());
     };
     let Value::Object(dict) = json else {
         return Err("Material must be a JSON object".to_string());
@@ -56,19 +203,61 @@ pub fn material_from_json_value(
             g: 1.0,
             b: 1.0,
         }))?;
-    let Value::Number(roughness) = dict.get("roughness").or(Some(&Value::Number(0.0))).unwrap()
-    else {
+    let Value::Number(roughness) = dict.get("roughness").unwrap_or(&Value::Number(0.0)) else {
         return Err("Roughness must be a number".to_string());
     };
     if *roughness < 0.0 || *roughness > 1.0 {
         return Err("Roughness must be between 0 and 1".to_string());
     }
-    let Value::Number(metallic) = dict.get("metallic").or(Some(&Value::Number(0.0))).unwrap()
-    else {
+    let Value::Number(metallic) = dict.get("metallic").unwrap_or(&Value::Number(0.0)) else {
         return Err("Metallic must be a number".to_string());
     };
     if *metallic < 0.0 || *metallic > 1.0 {
         return Err("Metallic must be between 0 and 1".to_string());
     }
-    Ok((albedo, *roughness, *metallic))
+    let Value::Number(transmission) = dict.get("transmission").unwrap_or(&Value::Number(0.0))
+    else {
+        return Err("Transmission must be a number".to_string());
+    };
+    if *transmission < 0.0 || *transmission > 1.0 {
+        return Err("Transmission must be between 0 and 1".to_string());
+    }
+    let Value::Number(ior) = dict.get("ior").unwrap_or(&Value::Number(1.5)) else {
+        return Err("IOR must be a number".to_string());
+    };
+    if *ior < 1.0 {
+        return Err("IOR must be at least 1".to_string());
+    }
+    let albedo_texture = dict
+        .get("albedoTexture")
+        .map(|json| crate::texture::from_json_value(json, assets))
+        .transpose()?;
+    let emission = dict
+        .get("emission")
+        .map(emission_from_json_value)
+        .unwrap_or(Ok(HDRColor::BLACK))?;
+    Ok(Material {
+        albedo,
+        albedo_texture,
+        roughness: *roughness,
+        metallic: *metallic,
+        transmission: *transmission,
+        ior: *ior,
+        emission,
+    })
+}
+
+fn emission_from_json_value(json: &Value) -> Result<HDRColor, String> {
+    let Value::Object(dict) = json else {
+        return Err("Emission must be a JSON object".to_string());
+    };
+    let color_json = dict.get("color").ok_or("Missing required field: color")?;
+    let color = hdr_color_from_json_value(color_json)?;
+    let Value::Number(strength) = dict.get("strength").unwrap_or(&Value::Number(1.0)) else {
+        return Err("Strength must be a number".to_string());
+    };
+    if *strength < 0.0 {
+        return Err("Strength must not be negative".to_string());
+    }
+    Ok(color * *strength)
 }
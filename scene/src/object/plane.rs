@@ -1,6 +1,11 @@
 use crate::{
-    object::{material_from_json_value, quadratic::Quadratic, quadric::Quadric, quartic::Quartic},
-    position_from_json_value, ImageCache, ImageLoader,
+    object::{
+        material_from_json_value, quadratic::Quadratic, quadric::Quadric, quartic::Quartic,
+        Material,
+    },
+    position_from_json_value,
+    texture::TexturePoint,
+    AssetCache, ImageLoader,
 };
 
 use super::RTObject;
@@ -10,14 +15,11 @@ use core::types::{
 };
 use jsonc::Value;
 use std::collections::HashMap;
-use types::LDRColor;
 
 #[derive(Clone, Debug)]
 pub struct Plane {
     pub position: Position,
-    pub albedo: LDRColor,
-    pub roughness: f64,
-    pub metallic: f64,
+    pub material: Material,
 
     pub c100: f64,
     pub c010: f64,
@@ -36,8 +38,8 @@ fn linear_roots(a: f64, b: f64) -> Vec<f64> {
     }
 }
 
-impl RTObject for Plane {
-    fn test(&self, ray: Ray) -> Vec<Hit> {
+impl Plane {
+    fn roots(&self, ray: Ray) -> Vec<f64> {
         let origin: Position = (ray.origin - self.position).into();
 
         let (a, b) = {
@@ -60,18 +62,51 @@ impl RTObject for Plane {
         };
 
         linear_roots(a, b)
+    }
+
+    /// Normal pointing out of the half-space holding `point` if it's inside.
+    fn outward_normal(&self) -> Direction {
+        let point = *(self.point - self.position);
+        let value = self.c100 * point.x + self.c010 * point.y + self.c001 * point.z + self.c000;
+        // the gradient points to where the value grows
+        if (value < 0.0) == self.is_point_inside {
+            normal(self)
+        } else {
+            -normal(self)
+        }
+    }
+}
+
+impl RTObject for Plane {
+    fn test(&self, ray: Ray) -> Vec<Hit> {
+        self.roots(ray)
             .into_iter()
             .filter(|t| *t >= 0.0)
-            .map(|distance| Hit {
-                distance,
-                normal: normal(self),
-                albedo: self.albedo,
-                is_front_face: true, // decided later
-                roughness: self.roughness,
-                metallic: self.metallic,
+            .map(|distance| {
+                let outward = self.outward_normal();
+                let is_front_face = outward.dot(ray.direction) < 0.0;
+                self.material
+                    .hit_at(ray, distance, outward, is_front_face, |position| {
+                        // world units along the plane
+                        let local = *(position - self.position);
+                        let (tangent, bitangent) = normal(self).tangent_frame();
+                        TexturePoint {
+                            u: local.dot(tangent),
+                            v: local.dot(bitangent),
+                            object: local,
+                            world: *self.position + local,
+                            footprint: [(0.0, 0.0); 2],
+                        }
+                    })
             })
             .collect()
     }
+
+    fn occluded(&self, ray: Ray, max_distance: f64) -> bool {
+        self.roots(ray)
+            .into_iter()
+            .any(|t| t > 0.0 && t < max_distance)
+    }
 }
 
 fn normal(thiz: &Plane) -> Direction {
@@ -80,14 +115,13 @@ fn normal(thiz: &Plane) -> Direction {
 
 pub fn from_json_value(
     dict: &HashMap<String, Value>,
-    image_cache: &ImageCache<impl ImageLoader>,
+    assets: &mut AssetCache<impl ImageLoader>,
 ) -> Result<Box<dyn RTObject + Send + Sync>, String> {
     let position = dict
         .get("position")
         .map(position_from_json_value)
         .unwrap_or(Ok(Position::new(Vec3::ZERO)))?;
-    let (albedo, roughness, metallic) =
-        material_from_json_value(dict.get("material"), image_cache)?;
+    let material = material_from_json_value(dict.get("material"), assets)?;
     let point =
         position_from_json_value(dict.get("point").ok_or("Missing required field: point")?)?;
     let is_point_inside = dict
@@ -240,9 +274,7 @@ pub fn from_json_value(
     {
         Ok(Box::new(Quartic {
             position,
-            albedo,
-            roughness,
-            metallic,
+            material,
             c400,
             c040,
             c004,
@@ -294,9 +326,7 @@ pub fn from_json_value(
     {
         Ok(Box::new(Quadratic {
             position,
-            albedo,
-            roughness,
-            metallic,
+            material,
             c300,
             c030,
             c003,
@@ -324,9 +354,7 @@ pub fn from_json_value(
     {
         Ok(Box::new(Quadric {
             position,
-            albedo,
-            roughness,
-            metallic,
+            material,
             c200,
             c020,
             c002,
@@ -343,9 +371,7 @@ pub fn from_json_value(
     } else {
         Ok(Box::new(Plane {
             position,
-            albedo,
-            roughness,
-            metallic,
+            material,
             c100,
             c010,
             c001,
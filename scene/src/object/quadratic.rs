@@ -1,17 +1,17 @@
 use core::types::{
     math::{Direction, Position, Vec3},
-    rt::{Hit, Ray},
+    rt::{Hit, Ray, RayCone},
 };
-use types::LDRColor;
 
-use super::{util::enhance_normal, RTObject};
+use super::{
+    util::{box_texture_point, enhance_normal},
+    Material, RTObject,
+};
 
 #[derive(Clone, Debug)]
 pub struct Quadratic {
     pub position: Position,
-    pub albedo: LDRColor,
-    pub roughness: f64,
-    pub metallic: f64,
+    pub material: Material,
 
     pub c300: f64,
     pub c030: f64,
@@ -89,7 +89,7 @@ fn cubic_roots(a: f64, b: f64, c: f64, d: f64) -> Vec<f64> {
 }
 
 impl Quadratic {
-    fn internal_test(&self, ray: Ray) -> Vec<Hit> {
+    fn roots(&self, ray: Ray) -> Vec<f64> {
         let origin: Position = (ray.origin - self.position).into();
 
         let (a, b, c, d) = {
@@ -206,15 +206,22 @@ impl Quadratic {
         };
 
         cubic_roots(a, b, c, d)
+    }
+
+    fn internal_test(&self, ray: Ray) -> Vec<Hit> {
+        let origin: Position = (ray.origin - self.position).into();
+
+        self.roots(ray)
             .into_iter()
             .filter(|t| *t >= 0.0)
-            .map(|distance| Hit {
-                distance,
-                normal: self.normal(origin + ray.direction * distance),
-                albedo: self.albedo,
-                is_front_face: true, // decided later
-                roughness: self.roughness,
-                metallic: self.metallic,
+            .map(|distance| {
+                let local = origin + ray.direction * distance;
+                let normal = self.normal(local);
+                // is_front_face is decided later
+                self.material
+                    .hit_at(ray, distance, normal, true, |position| {
+                        box_texture_point(*(position - self.position), self.position, *normal)
+                    })
             })
             .collect()
     }
@@ -258,13 +265,14 @@ impl Quadratic {
 impl RTObject for Quadratic {
     fn test(&self, ray: Ray) -> Vec<Hit> {
         let (inside_direction, inside_length) = (ray.origin - self.point).direction_and_length();
-        let internal = self.internal_test(Ray {
+        let crossings = self.roots(Ray {
             origin: self.point,
             direction: inside_direction,
+            cone: RayCone::default(),
         });
-        let inside = (internal
+        let inside = (crossings
             .into_iter()
-            .filter(|hit| hit.distance < inside_length)
+            .filter(|t| *t >= 0.0 && *t < inside_length)
             .count()
             % 2
             == 0)
@@ -275,14 +283,7 @@ impl RTObject for Quadratic {
         let mut is_front_face = false;
         if inside {
             is_front_face = true;
-            result.push(Hit {
-                distance: 0.0,
-                normal: -ray.direction,
-                albedo: self.albedo,
-                is_front_face,
-                roughness: self.roughness,
-                metallic: self.metallic,
-            });
+            result.push(self.material.hit(0.0, -ray.direction, is_front_face));
         }
         for hit in self.internal_test(ray).into_iter() {
             is_front_face = !is_front_face;
@@ -293,16 +294,16 @@ impl RTObject for Quadratic {
             });
         }
         if is_front_face {
-            result.push(Hit {
-                distance: f64::INFINITY,
-                normal: ray.direction,
-                albedo: self.albedo,
-                is_front_face: false,
-                roughness: self.roughness,
-                metallic: self.metallic,
-            });
+            result.push(self.material.hit(f64::INFINITY, ray.direction, false));
         }
 
         result
     }
+
+    fn occluded(&self, ray: Ray, max_distance: f64) -> bool {
+        // every root is a surface crossing, which side the ray starts on doesn't matter
+        self.roots(ray)
+            .into_iter()
+            .any(|t| t > 0.0 && t < max_distance)
+    }
 }
@@ -1,17 +1,17 @@
 use core::types::{
     math::{Direction, Position, Vec3},
-    rt::{Hit, Ray},
+    rt::{Hit, Ray, RayCone},
 };
-use types::LDRColor;
 
-use super::{util::enhance_normal, RTObject};
+use super::{
+    util::{box_texture_point, enhance_normal},
+    Material, RTObject,
+};
 
 #[derive(Clone, Debug)]
 pub struct Quadric {
     pub position: Position,
-    pub albedo: LDRColor,
-    pub roughness: f64,
-    pub metallic: f64,
+    pub material: Material,
 
     pub c200: f64,
     pub c020: f64,
@@ -29,7 +29,7 @@ pub struct Quadric {
 }
 
 impl Quadric {
-    fn internal_test(&self, ray: Ray) -> Option<(Hit, Hit)> {
+    fn roots(&self, ray: Ray) -> Option<(f64, f64)> {
         // Move the sphere to the origin for simplicity
         let origin: Position = (ray.origin - self.position).into();
 
@@ -94,48 +94,33 @@ impl Quadric {
                 (t2, t1)
             }
         };
+        Some((t1, t2))
+    }
+
+    fn internal_test(&self, ray: Ray) -> Option<(Hit, Hit)> {
+        let origin: Position = (ray.origin - self.position).into();
+
+        let (t1, t2) = self.roots(ray)?;
         if t2 < 0.0 {
             return None;
         }
 
+        let hit = |distance: f64, is_front_face| {
+            let local = origin + ray.direction * distance;
+            let normal = self.normal(local);
+            self.material
+                .hit_at(ray, distance, normal, is_front_face, |position| {
+                    box_texture_point(*(position - self.position), self.position, *normal)
+                })
+        };
+
         if t1 < 0.0 {
             Some((
-                Hit {
-                    distance: t2,
-                    normal: self.normal(origin + ray.direction * t2),
-                    albedo: self.albedo,
-                    is_front_face: true,
-                    roughness: self.roughness,
-                    metallic: self.metallic,
-                },
-                Hit {
-                    distance: f64::INFINITY,
-                    normal: ray.direction,
-                    albedo: self.albedo,
-                    is_front_face: false,
-                    roughness: self.roughness,
-                    metallic: self.metallic,
-                },
+                hit(t2, true),
+                self.material.hit(f64::INFINITY, ray.direction, false),
             ))
         } else {
-            Some((
-                Hit {
-                    distance: t1,
-                    normal: self.normal(origin + ray.direction * t1),
-                    albedo: self.albedo,
-                    is_front_face: true,
-                    roughness: self.roughness,
-                    metallic: self.metallic,
-                },
-                Hit {
-                    distance: t2,
-                    normal: self.normal(origin + ray.direction * t2),
-                    albedo: self.albedo,
-                    is_front_face: false,
-                    roughness: self.roughness,
-                    metallic: self.metallic,
-                },
-            ))
+            Some((hit(t1, true), hit(t2, false)))
         }
     }
 
@@ -163,6 +148,7 @@ impl RTObject for Quadric {
         let inside = (if let Some((hit1, hit2)) = self.internal_test(Ray {
             origin: self.point,
             direction: inside_direction,
+            cone: RayCone::default(),
         }) {
             (hit1.distance < inside_length) == (hit2.distance < inside_length)
         } else {
@@ -173,14 +159,7 @@ impl RTObject for Quadric {
 
         if let Some((hit1, hit2)) = self.internal_test(ray) {
             if inside {
-                result.push(Hit {
-                    distance: 0.0,
-                    normal: -ray.direction,
-                    albedo: self.albedo,
-                    is_front_face: true,
-                    roughness: self.roughness,
-                    metallic: self.metallic,
-                });
+                result.push(self.material.hit(0.0, -ray.direction, true));
                 result.push(Hit {
                     normal: enhance_normal(ray.direction, hit1.normal, false),
                     is_front_face: false,
@@ -191,14 +170,7 @@ impl RTObject for Quadric {
                     is_front_face: true,
                     ..hit2
                 });
-                result.push(Hit {
-                    distance: f64::INFINITY,
-                    normal: ray.direction,
-                    albedo: self.albedo,
-                    is_front_face: false,
-                    roughness: self.roughness,
-                    metallic: self.metallic,
-                });
+                result.push(self.material.hit(f64::INFINITY, ray.direction, false));
             } else {
                 result.push(Hit {
                     normal: enhance_normal(ray.direction, hit1.normal, true),
@@ -210,24 +182,17 @@ impl RTObject for Quadric {
                 });
             }
         } else if inside {
-            result.push(Hit {
-                distance: 0.0,
-                normal: -ray.direction,
-                albedo: self.albedo,
-                is_front_face: true,
-                roughness: self.roughness,
-                metallic: self.metallic,
-            });
-            result.push(Hit {
-                distance: f64::INFINITY,
-                normal: ray.direction,
-                albedo: self.albedo,
-                is_front_face: false,
-                roughness: self.roughness,
-                metallic: self.metallic,
-            });
+            result.push(self.material.hit(0.0, -ray.direction, true));
+            result.push(self.material.hit(f64::INFINITY, ray.direction, false));
         }
 
         result
     }
+
+    fn occluded(&self, ray: Ray, max_distance: f64) -> bool {
+        // both roots are surface crossings, which side the ray starts on doesn't matter
+        self.roots(ray).is_some_and(|(t1, t2)| {
+            (t1 > 0.0 && t1 < max_distance) || (t2 > 0.0 && t2 < max_distance)
+        })
+    }
 }
@@ -1,77 +1,28 @@
-use std::{collections::HashMap, sync::Arc};
+use std::collections::HashMap;
 
 use crate::{
+    light::area::{AreaLight, Shape},
     object::material_from_json_value,
     position_from_json_value,
-    texture::{DeserializableTexture, Texture},
-    ImageCache, ImageLoader,
+    texture::TexturePoint,
+    AssetCache, ImageLoader,
 };
 
-use super::RTObject;
+use super::{Material, RTObject};
 use core::types::{
-    math::{Direction, Position, Vec3},
-    rt::{Hit, Ray},
+    math::{Aabb, Direction, Position, Vec3},
+    rt::{Hit, Light, Ray},
 };
 use jsonc::Value;
-use types::LDRColor;
-
-#[derive(Clone, Debug)]
-pub struct DeserializableSphere {
-    radius: f64,
-    position: Position,
-    albedo: LDRColor,
-    roughness: f64,
-    metallic: f64,
-    texture: Option<DeserializableTexture>,
-}
-
-impl DeserializableSphere {
-    pub fn into_rt_object<T: ImageLoader>(
-        self,
-        image_cache: &mut ImageCache<T>,
-    ) -> Box<dyn RTObject + Send + Sync> {
-        Box::new(Sphere {
-            radius: self.radius,
-            position: self.position,
-            albedo: self.albedo,
-            roughness: self.roughness,
-            metallic: self.metallic,
-            texture: self.texture.map(|t| t.into_texture(image_cache)),
-        })
-    }
-}
 
 struct Sphere {
     radius: f64,
     position: Position,
-    albedo: LDRColor,
-    roughness: f64,
-    metallic: f64,
-    texture: Option<Arc<dyn Texture + Send + Sync>>,
+    material: Material,
 }
 
 impl Sphere {
-    fn albedo(&self, position: Position) -> LDRColor {
-        if let Some(texture) = &self.texture {
-            let dir = Direction::new(*(position - self.position));
-
-            let theta = dir.x.atan2(dir.y);
-            let phi = dir.z.acos();
-
-            let u = (theta + std::f64::consts::PI) / (2.0 * std::f64::consts::PI);
-            let v = phi / std::f64::consts::PI;
-
-            texture.get(u, v)
-        } else {
-            self.albedo
-        }
-    }
-}
-
-impl RTObject for Sphere {
-    fn test(&self, ray: Ray) -> Vec<Hit> {
-        let mut result = Vec::new();
-
+    fn roots(&self, ray: Ray) -> Option<(f64, f64)> {
         // Move the sphere to the origin for simplicity
         let origin: Position = (ray.origin - self.position).into();
 
@@ -84,62 +35,106 @@ impl RTObject for Sphere {
         let discriminant = b.powi(2) - 4.0 * a * c;
 
         if discriminant < 0.0 {
-            return result; // No intersection
+            return None; // No intersection
         }
 
         let sqrt_d = discriminant.sqrt();
-        let mut t1 = (-b - sqrt_d) / (2.0 * a);
-        let mut t2 = (-b + sqrt_d) / (2.0 * a);
-        if t1 > t2 {
-            (t1, t2) = (t2, t1);
+        let t1 = (-b - sqrt_d) / (2.0 * a);
+        let t2 = (-b + sqrt_d) / (2.0 * a);
+        if t1.is_nan() {
+            return None; // error
         }
+        Some(if t1 > t2 { (t2, t1) } else { (t1, t2) })
+    }
+
+    fn texture_point(&self, position: Position) -> TexturePoint {
+        let dir = Direction::new(*(position - self.position));
+
+        let theta = dir.x.atan2(dir.y);
+        let phi = dir.z.acos();
+
+        let u = (theta + std::f64::consts::PI) / (2.0 * std::f64::consts::PI);
+        let v = phi / std::f64::consts::PI;
+
+        TexturePoint {
+            u,
+            v,
+            object: *(position - self.position),
+            world: *position,
+            footprint: [(0.0, 0.0); 2],
+        }
+    }
+}
+
+impl RTObject for Sphere {
+    fn aabb(&self) -> Option<Aabb> {
+        let extent = Vec3::new(self.radius, self.radius, self.radius);
+        Some(Aabb::new(*self.position - extent, *self.position + extent))
+    }
+
+    fn test(&self, ray: Ray) -> Vec<Hit> {
+        let mut result = Vec::new();
+
+        let origin: Position = (ray.origin - self.position).into();
+        let Some((t1, t2)) = self.roots(ray) else {
+            return result;
+        };
 
         if t2 < 0.0 {
             return result; // No visible intersection
         }
-        if t1.is_nan() {
-            return result; // error
-        }
 
         if t1 < 0.0 {
             // If t1 is negative, ray started inside the sphere
-            result.push(Hit {
-                distance: 0.0,
-                normal: -ray.direction, // Opposite direction
-                albedo: self.albedo,
-                is_front_face: true,
-                roughness: self.roughness,
-                metallic: self.metallic,
-            });
+            // Opposite direction
+            result.push(self.material.hit(0.0, -ray.direction, true));
         } else {
             let normal: Vec3 = *(origin + ray.direction * t1) * 2.0;
-            result.push(Hit {
-                distance: t1,
-                normal: Direction::new(normal),
-                albedo: self.albedo(ray.origin + ray.direction * t1),
-                is_front_face: true,
-                roughness: self.roughness,
-                metallic: self.metallic,
-            });
+            result.push(
+                self.material
+                    .hit_at(ray, t1, Direction::new(normal), true, |position| {
+                        self.texture_point(position)
+                    }),
+            );
         }
 
         let normal: Vec3 = *(origin + ray.direction * t2) * 2.0;
-        result.push(Hit {
-            distance: t2,
-            normal: Direction::new(normal),
-            albedo: self.albedo(ray.origin + ray.direction * t2),
-            is_front_face: false,
-            roughness: self.roughness,
-            metallic: self.metallic,
-        });
+        result.push(
+            self.material
+                .hit_at(ray, t2, Direction::new(normal), false, |position| {
+                    self.texture_point(position)
+                }),
+        );
 
         result
     }
+
+    fn occluded(&self, ray: Ray, max_distance: f64) -> bool {
+        self.roots(ray).is_some_and(|(t1, t2)| {
+            (t1 > 0.0 && t1 < max_distance) || (t2 > 0.0 && t2 < max_distance)
+        })
+    }
+
+    fn emitter(&self) -> Option<Box<dyn Light + Send + Sync>> {
+        let emission = self.material.emission;
+        if emission.r.max(emission.g).max(emission.b) <= 0.0 {
+            return None;
+        }
+        // area lights take the intensity spread over their projected area
+        let area = std::f64::consts::PI * self.radius * self.radius;
+        Some(Box::new(AreaLight::new(
+            emission * area,
+            self.position,
+            Shape::Sphere {
+                radius: self.radius,
+            },
+        )))
+    }
 }
 
 pub fn from_json_value(
     dict: &HashMap<String, Value>,
-    image_cache: &ImageCache<impl ImageLoader>,
+    assets: &mut AssetCache<impl ImageLoader>,
 ) -> Result<Box<dyn RTObject + Send + Sync>, String> {
     let Value::Number(radius) = dict.get("radius").ok_or("Missing required field: radius")? else {
         return Err("Radius must be a number".to_string());
@@ -151,14 +146,10 @@ pub fn from_json_value(
         .get("position")
         .map(position_from_json_value)
         .unwrap_or(Ok(Position::new(Vec3::ZERO)))?;
-    let (albedo, roughness, metallic) =
-        material_from_json_value(dict.get("material"), image_cache)?;
+    let material = material_from_json_value(dict.get("material"), assets)?;
     Ok(Box::new(Sphere {
         radius: *radius,
         position,
-        albedo,
-        roughness,
-        metallic,
-        texture: None,
+        material,
     }))
 }
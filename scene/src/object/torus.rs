@@ -0,0 +1,238 @@
+use std::collections::HashMap;
+
+use core::types::{
+    math::{Aabb, Direction, Position, Vec3},
+    rt::{Hit, Ray},
+};
+use jsonc::Value;
+
+use crate::{
+    object::material_from_json_value, position_from_json_value, texture::TexturePoint, AssetCache,
+    ImageLoader,
+};
+
+use super::{
+    quartic::quartic_roots,
+    util::{azimuth, positive_number},
+    Material, RTObject,
+};
+
+/// Ring around the y axis through `position`, with a tube of radius
+/// `minor_radius` along a circle of radius `major_radius`.
+struct Torus {
+    position: Position,
+    major_radius: f64,
+    minor_radius: f64,
+    material: Material,
+}
+
+impl Torus {
+    /// (|p|² + R² - r²)² - 4 R² (x² + z²) and its derivative along the ray.
+    fn implicit(&self, origin: Vec3, direction: Vec3, t: f64) -> (f64, f64) {
+        let p = origin + direction * t;
+        let r2 = self.major_radius * self.major_radius;
+        let k = p.dot(p) + r2 - self.minor_radius * self.minor_radius;
+        let value = k * k - 4.0 * r2 * (p.x * p.x + p.z * p.z);
+        let derivative =
+            4.0 * k * p.dot(direction) - 8.0 * r2 * (p.x * direction.x + p.z * direction.z);
+        (value, derivative)
+    }
+
+    /// Whether the ray starts inside, and the positive distances where it
+    /// enters or leaves the solid, in order.
+    fn crossings(&self, ray: Ray) -> (bool, Vec<f64>) {
+        let d = *ray.direction;
+        let o = *(ray.origin - self.position);
+        // start near the torus, the quartic loses precision with far origins
+        let bound = self.major_radius + self.minor_radius;
+        let shift = (o.length() - bound).max(0.0);
+        let o = o + d * shift;
+
+        let r2 = self.major_radius * self.major_radius;
+        let g = d.dot(d);
+        let f = o.dot(d);
+        let e = o.dot(o) + r2 - self.minor_radius * self.minor_radius;
+        let mut roots: Vec<f64> = quartic_roots(
+            g * g,
+            4.0 * g * f,
+            4.0 * f * f + 2.0 * g * e - 4.0 * r2 * (d.x * d.x + d.z * d.z),
+            4.0 * f * e - 8.0 * r2 * (o.x * d.x + o.z * d.z),
+            e * e - 4.0 * r2 * (o.x * o.x + o.z * o.z),
+        )
+        .into_iter()
+        .map(|mut t| {
+            // the closed form is only approximate on nearly double roots
+            for _ in 0..2 {
+                let (value, derivative) = self.implicit(o, d, t);
+                if derivative.abs() > 1e-12 {
+                    t -= value / derivative;
+                }
+            }
+            t
+        })
+        .filter(|t| *t > -shift && t.is_finite())
+        .collect();
+        roots.sort_by(|a, b| a.total_cmp(b));
+
+        // tangent rays give a double root, or two close ones, or one that
+        // is slightly off, so keep only the roots between a piece of the
+        // ray inside and one outside
+        let inside = |a: f64, b: f64| self.implicit(o, d, (a + b) / 2.0).0 < 0.0;
+        let Some(first) = roots.first() else {
+            return (false, roots);
+        };
+        let starts_inside = inside(-shift, *first);
+        let mut was_inside = starts_inside;
+        let mut crossings = Vec::with_capacity(roots.len());
+        for (i, t) in roots.iter().enumerate() {
+            // the torus is bounded, the ray always ends outside
+            let is_inside = roots.get(i + 1).is_some_and(|next| inside(*t, *next));
+            if is_inside != was_inside {
+                crossings.push(t + shift);
+                was_inside = is_inside;
+            }
+        }
+        (starts_inside, crossings)
+    }
+
+    /// Outward normal at a point of the surface, relative to the center.
+    fn normal(&self, point: Vec3) -> Vec3 {
+        let ring = Vec3::new(point.x, 0.0, point.z);
+        let ring = if ring.length_square() > 0.0 {
+            ring.normalize() * self.major_radius
+        } else {
+            ring
+        };
+        point - ring
+    }
+
+    /// u goes around the ring, v around the tube from its inner side.
+    fn texture_point(&self, position: Position) -> TexturePoint {
+        let local = *(position - self.position);
+        let across = local.x.hypot(local.z) - self.major_radius;
+        TexturePoint {
+            u: azimuth(local),
+            v: local.y.atan2(-across) / (2.0 * std::f64::consts::PI) + 0.5,
+            object: local,
+            world: *position,
+            footprint: [(0.0, 0.0); 2],
+        }
+    }
+}
+
+impl RTObject for Torus {
+    fn aabb(&self) -> Option<Aabb> {
+        let radius = self.major_radius + self.minor_radius;
+        let extent = Vec3::new(radius, self.minor_radius, radius);
+        Some(Aabb::new(*self.position - extent, *self.position + extent))
+    }
+
+    fn test(&self, ray: Ray) -> Vec<Hit> {
+        let origin = *(ray.origin - self.position);
+        let (starts_inside, crossings) = self.crossings(ray);
+        let mut result = Vec::with_capacity(crossings.len() + 1);
+        if starts_inside {
+            result.push(self.material.hit(0.0, -ray.direction, true));
+        }
+        result.extend(crossings.into_iter().enumerate().map(|(i, t)| {
+            let normal = self.normal(origin + *ray.direction * t);
+            let is_front_face = (i % 2 == 0) != starts_inside;
+            self.material
+                .hit_at(ray, t, Direction::new(normal), is_front_face, |position| {
+                    self.texture_point(position)
+                })
+        }));
+        result
+    }
+
+    fn occluded(&self, ray: Ray, max_distance: f64) -> bool {
+        self.crossings(ray)
+            .1
+            .first()
+            .is_some_and(|t| *t < max_distance)
+    }
+}
+
+pub fn from_json_value(
+    dict: &HashMap<String, Value>,
+    assets: &mut AssetCache<impl ImageLoader>,
+) -> Result<Box<dyn RTObject + Send + Sync>, String> {
+    let major_radius = positive_number(dict, "majorRadius")?;
+    let minor_radius = positive_number(dict, "minorRadius")?;
+    let position = dict
+        .get("position")
+        .map(position_from_json_value)
+        .unwrap_or(Ok(Position::new(Vec3::ZERO)))?;
+    let material = material_from_json_value(dict.get("material"), assets)?;
+    Ok(Box::new(Torus {
+        position,
+        major_radius,
+        minor_radius,
+        material,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::util::test::{assert_hits, ray};
+    use super::*;
+
+    /// Tube of radius 0.5 around a circle of radius 2.
+    fn torus() -> Torus {
+        Torus {
+            position: Position::new(Vec3::ZERO),
+            major_radius: 2.0,
+            minor_radius: 0.5,
+            material: Material::default(),
+        }
+    }
+
+    fn check(origin: Vec3, direction: Vec3, distances: &[f64]) {
+        let ray = ray(origin, direction);
+        assert_hits(&torus().test(ray), distances, ray);
+    }
+
+    #[test]
+    fn hits() {
+        // through the hole
+        check(Vec3::new(0.0, 5.0, 0.0), -Vec3::Y, &[]);
+        // through both tubes
+        check(Vec3::new(-5.0, 0.0, 0.0), Vec3::X, &[2.5, 3.5, 6.5, 7.5]);
+        check(Vec3::new(-5.0, 0.3, 0.0), Vec3::X, &[2.6, 3.4, 6.6, 7.4]);
+        check(Vec3::new(2.0, 5.0, 0.0), -Vec3::Y, &[4.5, 5.5]);
+        // from inside a tube
+        check(Vec3::new(2.0, 0.0, 0.0), Vec3::X, &[0.0, 0.5]);
+        check(Vec3::new(2.0, 0.0, 0.0), -Vec3::X, &[0.0, 0.5, 3.5, 4.5]);
+        // from far away
+        check(
+            Vec3::new(-1e4, 0.0, 0.0),
+            Vec3::X,
+            &[9997.5, 9998.5, 10001.5, 10002.5],
+        );
+    }
+
+    #[test]
+    fn tangent_rays_enter_and_leave() {
+        for (origin, direction) in [
+            // over the top of both tubes
+            (Vec3::new(-5.0, 0.5, 0.0), Vec3::X),
+            // along the outer and inner equators
+            (Vec3::new(2.5, 0.0, -5.0), Vec3::Z),
+            (Vec3::new(1.5, 0.0, -5.0), Vec3::Z),
+            (Vec3::new(-1.5, 0.0, 5.0), -Vec3::Z),
+            // slanted, touching the top of a tube at (2, 0.5, 0)
+            (Vec3::new(-3.0, 0.5, 0.0), Vec3::new(5.0, 0.0, 1e-9)),
+        ] {
+            let hits = torus().test(ray(origin, direction));
+            assert!(
+                hits.len().is_multiple_of(2),
+                "{} hits from {:?}",
+                hits.len(),
+                origin
+            );
+            for (i, hit) in hits.iter().enumerate() {
+                assert_eq!(hit.is_front_face, i % 2 == 0);
+            }
+        }
+    }
+}
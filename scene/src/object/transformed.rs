@@ -0,0 +1,114 @@
+use core::{
+    random::Rng,
+    types::{
+        math::{Aabb, Direction, Position, Transform},
+        rt::{Hit, Light, RTObject, Ray, RayCone},
+    },
+};
+use types::HDRColor;
+
+/// An object placed by an affine transform. Rays are moved into the
+/// object's space, and hits back out of it.
+struct Transformed {
+    object: Box<dyn RTObject + Send + Sync>,
+    transform: Transform,
+}
+
+impl Transformed {
+    /// The ray in object space, and the object space length of one unit along it.
+    fn local_ray(&self, ray: Ray) -> (Ray, f64) {
+        let inverse = self.transform.inverse();
+        let direction = inverse.vector(*ray.direction);
+        let scale = direction.length();
+        let local = Ray {
+            origin: inverse.position(ray.origin),
+            direction: Direction::new(direction),
+            cone: RayCone {
+                width: ray.cone.width * scale,
+                spread: ray.cone.spread,
+            },
+        };
+        (local, scale)
+    }
+}
+
+impl RTObject for Transformed {
+    fn test(&self, ray: Ray) -> Vec<Hit> {
+        let (local, scale) = self.local_ray(ray);
+        self.object
+            .test(local)
+            .into_iter()
+            .map(|hit| Hit {
+                distance: hit.distance / scale,
+                normal: self.transform.normal(hit.normal),
+                ..hit
+            })
+            .collect()
+    }
+
+    fn occluded(&self, ray: Ray, max_distance: f64) -> bool {
+        let (local, scale) = self.local_ray(ray);
+        self.object.occluded(local, max_distance * scale)
+    }
+
+    fn aabb(&self) -> Option<Aabb> {
+        Some(self.transform.aabb(self.object.aabb()?))
+    }
+
+    fn emitter(&self) -> Option<Box<dyn Light + Send + Sync>> {
+        // lights are sampled by solid angle, which only survives transforms keeping angles
+        self.transform.uniform_scale()?;
+        let light = self.object.emitter()?;
+        Some(Box::new(TransformedLight {
+            light,
+            transform: self.transform,
+        }))
+    }
+}
+
+struct TransformedLight {
+    light: Box<dyn Light + Send + Sync>,
+    transform: Transform,
+}
+
+impl TransformedLight {
+    fn to_world(
+        &self,
+        (color, direction, distance): (HDRColor, Direction, f64),
+    ) -> (HDRColor, Direction, f64) {
+        let direction = self.transform.vector(*direction);
+        (
+            color,
+            Direction::new(direction),
+            distance * direction.length(),
+        )
+    }
+}
+
+impl Light for TransformedLight {
+    fn test(&self, position: Position) -> Option<(HDRColor, Direction, f64)> {
+        let local = self.transform.inverse().position(position);
+        self.light.test(local).map(|sample| self.to_world(sample))
+    }
+
+    fn sample(
+        &self,
+        position: Position,
+        count: usize,
+        rng: &mut Rng,
+    ) -> Vec<(HDRColor, Direction, f64)> {
+        let local = self.transform.inverse().position(position);
+        self.light
+            .sample(local, count, rng)
+            .into_iter()
+            .map(|sample| self.to_world(sample))
+            .collect()
+    }
+}
+
+pub fn new(
+    object: Box<dyn RTObject + Send + Sync>,
+    transform: Transform,
+) -> Box<dyn RTObject + Send + Sync> {
+    Box::new(Transformed { object, transform })
+}
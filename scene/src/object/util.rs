@@ -1,4 +1,14 @@
-use core::types::math::Direction;
+use std::collections::HashMap;
+
+use core::types::{
+    math::{Direction, Position, Vec3},
+    rt::{Hit, Ray},
+};
+use jsonc::Value;
+
+use crate::texture::TexturePoint;
+
+use super::Material;
 
 pub fn enhance_normal(
     ray_direction: Direction,
@@ -11,3 +21,158 @@ pub fn enhance_normal(
         -face_normal
     }
 }
+
+/// UV coordinates of `point` projected along the axis `normal` is closest to,
+/// in the units of `point`, with v growing downward on the side faces.
+pub fn box_uv(point: Vec3, normal: Vec3) -> (f64, f64) {
+    let (x, y, z) = (normal.x.abs(), normal.y.abs(), normal.z.abs());
+    if x >= y && x >= z {
+        (-point.z * normal.x.signum(), -point.y)
+    } else if y >= z {
+        (point.x, point.z * normal.y.signum())
+    } else {
+        (point.x * normal.z.signum(), -point.y)
+    }
+}
+
+/// Texture lookup point for `local`, relative to the primitive at `position`,
+/// with [`box_uv`] coordinates.
+pub fn box_texture_point(local: Vec3, position: Position, normal: Vec3) -> TexturePoint {
+    let (u, v) = box_uv(local, normal);
+    TexturePoint {
+        u,
+        v,
+        object: local,
+        world: *position + local,
+        footprint: [(0.0, 0.0); 2],
+    }
+}
+
+/// Hits of a ray through a convex solid, entering and leaving it at the
+/// given distances with the given outward normals. `point` maps positions
+/// near a hit to texture points, knowing the normal of the hit.
+pub fn convex_hits(
+    material: &Material,
+    ray: Ray,
+    entry: (f64, Vec3),
+    exit: (f64, Vec3),
+    point: impl Fn(Position, Vec3) -> TexturePoint,
+) -> Vec<Hit> {
+    let mut result = Vec::new();
+    if exit.0 < 0.0 {
+        return result;
+    }
+
+    if entry.0 < 0.0 {
+        // the ray starts inside
+        result.push(material.hit(0.0, -ray.direction, true));
+    } else {
+        result.push(
+            material.hit_at(ray, entry.0, Direction::new(entry.1), true, |position| {
+                point(position, entry.1)
+            }),
+        );
+    }
+    result.push(
+        material.hit_at(ray, exit.0, Direction::new(exit.1), false, |position| {
+            point(position, exit.1)
+        }),
+    );
+
+    result
+}
+
+/// The ranges of t where a t² + b t + c <= 0, in order.
+pub fn inside_quadratic(a: f64, b: f64, c: f64) -> Option<[Option<(f64, f64)>; 2]> {
+    let all = (f64::NEG_INFINITY, f64::INFINITY);
+    if a.abs() < 1e-12 {
+        if b.abs() < 1e-12 {
+            return (c <= 0.0).then_some([Some(all), None]);
+        }
+        let root = -c / b;
+        return Some([
+            Some(if b > 0.0 {
+                (f64::NEG_INFINITY, root)
+            } else {
+                (root, f64::INFINITY)
+            }),
+            None,
+        ]);
+    }
+
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return (a < 0.0).then_some([Some(all), None]);
+    }
+    let sqrt_d = discriminant.sqrt();
+    let (t1, t2) = ((-b - sqrt_d) / (2.0 * a), (-b + sqrt_d) / (2.0 * a));
+    let (t1, t2) = (t1.min(t2), t1.max(t2));
+    Some(if a > 0.0 {
+        [Some((t1, t2)), None]
+    } else {
+        [Some((f64::NEG_INFINITY, t1)), Some((t2, f64::INFINITY))]
+    })
+}
+
+/// Angle around the y axis as a texture coordinate in 0..1.
+pub fn azimuth(point: Vec3) -> f64 {
+    point.z.atan2(point.x) / (2.0 * std::f64::consts::PI) + 0.5
+}
+
+/// The number at `name`, which must be present and positive.
+pub fn positive_number(dict: &HashMap<String, Value>, name: &str) -> Result<f64, String> {
+    let Value::Number(number) = dict
+        .get(name)
+        .ok_or(format!("Missing required field: {}", name))?
+    else {
+        return Err(format!("{} must be a number", name));
+    };
+    if *number <= 0.0 {
+        return Err(format!("{} must be greater than 0", name));
+    }
+    Ok(*number)
+}
+
+#[cfg(test)]
+pub mod test {
+    use core::types::{
+        math::{Direction, Position, Vec3},
+        rt::{Hit, Ray, RayCone},
+    };
+
+    pub fn ray(origin: Vec3, direction: Vec3) -> Ray {
+        Ray {
+            origin: Position::new(origin),
+            direction: Direction::new(direction),
+            cone: RayCone::default(),
+        }
+    }
+
+    /// Checks that `hits` are at `distances`, alternately entering and
+    /// leaving the solid, with normals facing the side they report.
+    pub fn assert_hits(hits: &[Hit], distances: &[f64], ray: Ray) {
+        let actual: Vec<f64> = hits.iter().map(|hit| hit.distance).collect();
+        assert_eq!(hits.len(), distances.len(), "hits at {:?}", actual);
+        for (i, (hit, distance)) in hits.iter().zip(distances).enumerate() {
+            assert!(
+                (hit.distance - distance).abs() < 1e-6,
+                "hits at {:?}, expected {:?}",
+                actual,
+                distances
+            );
+            assert_eq!(hit.is_front_face, i % 2 == 0, "hit {} at {}", i, distance);
+            let facing = hit.normal.dot(ray.direction);
+            assert!(
+                if hit.is_front_face {
+                    facing <= 0.0
+                } else {
+                    facing >= 0.0
+                },
+                "hit {} at {} has normal {:?}",
+                i,
+                distance,
+                *hit.normal
+            );
+        }
+    }
+}
@@ -0,0 +1,290 @@
+use std::{f64::consts::PI, sync::Arc};
+
+use core::types::{
+    math::{Direction, Vec3},
+    rt::Light,
+};
+use jsonc::Value;
+use types::HDRColor;
+
+use crate::{
+    angle_from_json_value, direction_from_json_value, hdr_color_from_json_value,
+    light::directional::DirectionalLight, AssetCache, Image, ImageLoader,
+};
+
+pub type Sky = Arc<dyn Fn(Direction) -> HDRColor + Send + Sync>;
+
+/// Parse the `sky` block of a scene into the radiance seen in each direction,
+/// and the sun light if the sky asks for one.
+pub fn from_json_value<T: ImageLoader>(
+    json: &Value,
+    assets: &mut AssetCache<T>,
+) -> Result<(Sky, Option<Box<dyn Light + Send + Sync>>), String> {
+    let Value::Object(dict) = json else {
+        return Err("sky must be a JSON object".to_string());
+    };
+
+    let Value::String(type_str) = dict.get("type").ok_or("Missing required field: type")? else {
+        return Err("sky must have a 'type' field with string value".to_string());
+    };
+
+    let intensity = match dict.get("intensity") {
+        Some(Value::Number(i)) if *i >= 0.0 => *i,
+        Some(_) => return Err("intensity must be a non-negative number".to_string()),
+        None => 1.0,
+    };
+
+    match type_str.as_str() {
+        "image" => {
+            let Value::String(path) = dict.get("path").ok_or("Missing required field: path")?
+            else {
+                return Err("path must be a string".to_string());
+            };
+            let rotation = match dict.get("rotation") {
+                Some(json) => angle_from_json_value(json)?,
+                None => 0.0,
+            };
+            let map = EnvironmentMap {
+                image: assets.load(path)?,
+                rotation,
+                intensity,
+            };
+            Ok((Arc::new(move |direction| map.get(direction)), None))
+        }
+        "gradient" => {
+            let stop = |key: &str| match dict.get(key) {
+                Some(json) => hdr_color_from_json_value(json).map(Some),
+                None => Ok(None),
+            };
+            let zenith = stop("zenith")?.ok_or("Missing required field: zenith")?;
+            let nadir = stop("nadir")?.ok_or("Missing required field: nadir")?;
+            let gradient = Gradient {
+                zenith: zenith * intensity,
+                horizon: stop("horizon")?.map(|horizon| horizon * intensity),
+                nadir: nadir * intensity,
+            };
+            Ok((Arc::new(move |direction| gradient.get(direction)), None))
+        }
+        "preetham" => {
+            let sun_json = dict
+                .get("sunDirection")
+                .ok_or("Missing required field: sunDirection")?;
+            let sun_direction = direction_from_json_value(sun_json)?;
+            if sun_direction.y <= 0.0 {
+                return Err("sunDirection must point above the horizon".to_string());
+            }
+
+            let turbidity = match dict.get("turbidity") {
+                Some(Value::Number(t)) if (2.0..=10.0).contains(t) => *t,
+                Some(_) => return Err("turbidity must be a number between 2 and 10".to_string()),
+                None => 3.0,
+            };
+
+            let sun = match dict.get("sunIntensity") {
+                Some(Value::Number(i)) if *i >= 0.0 => {
+                    let color = sun_transmittance(sun_direction, turbidity) * *i;
+                    let light: Box<dyn Light + Send + Sync> =
+                        Box::new(DirectionalLight::new(color, -sun_direction));
+                    Some(light)
+                }
+                Some(_) => return Err("sunIntensity must be a non-negative number".to_string()),
+                None => None,
+            };
+
+            let sky = Preetham::new(sun_direction, turbidity, intensity);
+            Ok((Arc::new(move |direction| sky.get(direction)), sun))
+        }
+        _ => Err(format!("Unknown sky type: {}", type_str)),
+    }
+}
+
+/// Vertical blend from `zenith` through the optional `horizon` to `nadir`.
+struct Gradient {
+    zenith: HDRColor,
+    horizon: Option<HDRColor>,
+    nadir: HDRColor,
+}
+
+impl Gradient {
+    fn get(&self, direction: Direction) -> HDRColor {
+        let lerp = |a: HDRColor, b: HDRColor, t: f64| a * (1.0 - t) + b * t;
+        let y = direction.y.clamp(-1.0, 1.0);
+        match self.horizon {
+            Some(horizon) if y >= 0.0 => lerp(horizon, self.zenith, y),
+            Some(horizon) => lerp(horizon, self.nadir, -y),
+            None => lerp(self.nadir, self.zenith, (y + 1.0) / 2.0),
+        }
+    }
+}
+
+/// Converts the model's luminance in kcd/m² to scene radiance, putting a
+/// clear midday sky at about 1.
+const PREETHAM_SCALE: f64 = 0.1;
+
+/// Preetham, Shirley and Smits' analytic daylight model.
+///
+/// The model is only defined above the horizon, directions below it get the
+/// radiance of the horizon.
+struct Preetham {
+    sun_direction: Direction,
+    /// Perez coefficients A to E for luminance and the x and y chromaticities.
+    coefficients: [[f64; 5]; 3],
+    /// Zenith values divided by the Perez function at the zenith.
+    scale: [f64; 3],
+    intensity: f64,
+}
+
+impl Preetham {
+    fn new(sun_direction: Direction, turbidity: f64, intensity: f64) -> Self {
+        let t = turbidity;
+        let coefficients = [
+            [
+                0.1787 * t - 1.4630,
+                -0.3554 * t + 0.4275,
+                -0.0227 * t + 5.3251,
+                0.1206 * t - 2.5771,
+                -0.0670 * t + 0.3703,
+            ],
+            [
+                -0.0193 * t - 0.2592,
+                -0.0665 * t + 0.0008,
+                -0.0004 * t + 0.2125,
+                -0.0641 * t - 0.8989,
+                -0.0033 * t + 0.0452,
+            ],
+            [
+                -0.0167 * t - 0.2608,
+                -0.0950 * t + 0.0092,
+                -0.0079 * t + 0.2102,
+                -0.0441 * t - 1.6537,
+                -0.0109 * t + 0.0529,
+            ],
+        ];
+
+        let theta_s = sun_direction.y.acos();
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_s);
+        let zenith_luminance = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+        let cubic =
+            |c: [f64; 4]| c[0] * theta_s.powi(3) + c[1] * theta_s.powi(2) + c[2] * theta_s + c[3];
+        let zenith_x = t * t * cubic([0.00166, -0.00375, 0.00209, 0.0])
+            + t * cubic([-0.02903, 0.06377, -0.03202, 0.00394])
+            + cubic([0.11693, -0.21196, 0.06052, 0.25886]);
+        let zenith_y = t * t * cubic([0.00275, -0.00610, 0.00317, 0.0])
+            + t * cubic([-0.04214, 0.08970, -0.04153, 0.00516])
+            + cubic([0.15346, -0.26756, 0.06670, 0.26688]);
+
+        let zenith = [zenith_luminance, zenith_x, zenith_y];
+        let scale = std::array::from_fn(|i| zenith[i] / perez(coefficients[i], 0.0, theta_s));
+
+        Preetham {
+            sun_direction,
+            coefficients,
+            scale,
+            intensity,
+        }
+    }
+
+    fn get(&self, direction: Direction) -> HDRColor {
+        // lift directions to just above the horizon, where the Perez function still converges
+        let up = if direction.y >= 1e-3 {
+            direction
+        } else {
+            let horizontal = Vec3::new(direction.x, 0.0, direction.z);
+            let horizontal = if horizontal.length() > 1e-9 {
+                *Direction::new(horizontal)
+            } else {
+                Vec3::new(1.0, 0.0, 0.0)
+            };
+            Direction::new(horizontal + Vec3::new(0.0, 1e-3, 0.0))
+        };
+        let theta = up.y.acos();
+        let gamma = up.dot(self.sun_direction).clamp(-1.0, 1.0).acos();
+
+        let [luminance, x, y]: [f64; 3] =
+            std::array::from_fn(|i| self.scale[i] * perez(self.coefficients[i], theta, gamma));
+        xyy_to_rgb(x, y, luminance * PREETHAM_SCALE * self.intensity)
+    }
+}
+
+fn perez([a, b, c, d, e]: [f64; 5], theta: f64, gamma: f64) -> f64 {
+    (1.0 + a * (b / theta.cos()).exp()) * (1.0 + c * (d * gamma).exp() + e * gamma.cos().powi(2))
+}
+
+/// CIE xyY to linear sRGB.
+fn xyy_to_rgb(x: f64, y: f64, luminance: f64) -> HDRColor {
+    let big_x = x * luminance / y;
+    let big_z = (1.0 - x - y) * luminance / y;
+    HDRColor {
+        r: (3.2406 * big_x - 1.5372 * luminance - 0.4986 * big_z).max(0.0),
+        g: (-0.9689 * big_x + 1.8758 * luminance + 0.0415 * big_z).max(0.0),
+        b: (0.0557 * big_x - 0.2040 * luminance + 1.0570 * big_z).max(0.0),
+    }
+}
+
+/// Fraction of sunlight reaching the ground through Rayleigh and aerosol
+/// scattering, at red, green and blue wavelengths.
+fn sun_transmittance(sun_direction: Direction, turbidity: f64) -> HDRColor {
+    let theta_s = sun_direction.y.acos();
+    let optical_mass = 1.0 / (theta_s.cos() + 0.15 * (93.885 - theta_s.to_degrees()).powf(-1.253));
+    // Angstrom's turbidity coefficient, with wavelength exponent 1.3
+    let beta = 0.04608 * turbidity - 0.04586;
+    let transmittance = |micrometers: f64| {
+        let rayleigh = (-optical_mass * 0.008735 * micrometers.powf(-4.08)).exp();
+        let aerosol = (-optical_mass * beta * micrometers.powf(-1.3)).exp();
+        rayleigh * aerosol
+    };
+    HDRColor {
+        r: transmittance(0.65),
+        g: transmittance(0.57),
+        b: transmittance(0.475),
+    }
+}
+
+/// Equirectangular image around the scene, +Y at the top row.
+struct EnvironmentMap {
+    image: Arc<dyn Image + Send + Sync>,
+    /// Counterclockwise rotation around +Y, seen from above.
+    rotation: f64,
+    intensity: f64,
+}
+
+impl EnvironmentMap {
+    fn get(&self, direction: Direction) -> HDRColor {
+        let phi = direction.x.atan2(direction.z) + self.rotation;
+        let theta = direction.y.clamp(-1.0, 1.0).acos();
+        let u = (phi / (2.0 * PI) + 0.5).rem_euclid(1.0);
+        let v = theta / PI;
+
+        let width = self.image.width();
+        let height = self.image.height();
+        // texel centers sit at half coordinates; wrap around horizontally, clamp at the poles
+        let x = u * width as f64 - 0.5;
+        let y = (v * height as f64 - 0.5).clamp(0.0, (height - 1) as f64);
+        let x0 = x.floor();
+        let y0 = y.floor();
+        let dx = x - x0;
+        let dy = y - y0;
+        let column = |x: f64| (x as i64).rem_euclid(width as i64) as usize;
+        let (x0, x1) = (column(x0), column(x0 + 1.0));
+        let (y0, y1) = (y0 as usize, (y0 as usize + 1).min(height - 1));
+
+        let mut color = [0.0; 3];
+        for (x, y, weight) in [
+            (x0, y0, (1.0 - dx) * (1.0 - dy)),
+            (x1, y0, dx * (1.0 - dy)),
+            (x0, y1, (1.0 - dx) * dy),
+            (x1, y1, dx * dy),
+        ] {
+            let texel = self.image.get(x, y);
+            for (channel, value) in color.iter_mut().zip(texel) {
+                *channel += value * weight;
+            }
+        }
+
+        HDRColor {
+            r: color[0] * self.intensity,
+            g: color[1] * self.intensity,
+            b: color[2] * self.intensity,
+        }
+    }
+}
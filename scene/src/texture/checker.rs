@@ -0,0 +1,35 @@
+use types::LDRColor;
+
+use super::{Space, Texture, TexturePoint};
+
+/// Alternating cells, squares in UV space and cubes in object or world space.
+#[derive(Clone, Debug)]
+pub struct CheckerTexture {
+    space: Space,
+    /// Cells per unit.
+    scale: f64,
+    colors: (LDRColor, LDRColor),
+}
+
+impl CheckerTexture {
+    pub fn new(space: Space, scale: f64, colors: (LDRColor, LDRColor)) -> Self {
+        CheckerTexture {
+            space,
+            scale,
+            colors,
+        }
+    }
+}
+
+impl Texture for CheckerTexture {
+    fn get(&self, point: &TexturePoint) -> LDRColor {
+        // the nudge keeps surfaces lying on a cell boundary from flickering between cells
+        let p = self.space.point(point) * self.scale;
+        let cell = |x: f64| (x + 1e-6).floor() as i64;
+        if (cell(p.x) + cell(p.y) + cell(p.z)).rem_euclid(2) == 0 {
+            self.colors.0
+        } else {
+            self.colors.1
+        }
+    }
+}
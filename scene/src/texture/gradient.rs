@@ -0,0 +1,58 @@
+use core::types::math::Vec3;
+use types::LDRColor;
+
+use super::{lerp_color, Space, Texture, TexturePoint};
+
+#[derive(Clone, Copy, Debug)]
+pub enum GradientShape {
+    /// Along a direction from the origin, ignoring its length.
+    Linear(Vec3),
+    /// Outward from a center.
+    Radial(Vec3),
+}
+
+/// Blend between two colors over `1 / scale` units, clamped beyond.
+#[derive(Clone, Debug)]
+pub struct GradientTexture {
+    shape: GradientShape,
+    space: Space,
+    scale: f64,
+    colors: (LDRColor, LDRColor),
+}
+
+impl GradientTexture {
+    pub fn new(
+        shape: GradientShape,
+        space: Space,
+        scale: f64,
+        colors: (LDRColor, LDRColor),
+    ) -> Self {
+        let shape = match shape {
+            GradientShape::Linear(direction) => {
+                GradientShape::Linear(direction * (1.0 / direction.length()))
+            }
+            radial => radial,
+        };
+        GradientTexture {
+            shape,
+            space,
+            scale,
+            colors,
+        }
+    }
+}
+
+impl Texture for GradientTexture {
+    fn get(&self, point: &TexturePoint) -> LDRColor {
+        let p = self.space.point(point);
+        let t = match self.shape {
+            GradientShape::Linear(direction) => p.dot(direction),
+            GradientShape::Radial(center) => (p - center).length(),
+        };
+        lerp_color(
+            self.colors.0,
+            self.colors.1,
+            (t * self.scale).clamp(0.0, 1.0),
+        )
+    }
+}
@@ -0,0 +1,142 @@
+use std::sync::{Arc, OnceLock};
+
+use crate::Image;
+
+/// An image and its successively halved copies, down to a single texel.
+/// The copies are only built once a level past the image is asked for.
+pub struct MipMap {
+    image: Arc<dyn Image + Send + Sync>,
+    smaller: OnceLock<Vec<MipLevel>>,
+}
+
+impl MipMap {
+    pub fn new(image: Arc<dyn Image + Send + Sync>) -> Self {
+        MipMap {
+            image,
+            smaller: OnceLock::new(),
+        }
+    }
+
+    /// The full resolution image.
+    pub fn image(&self) -> &Arc<dyn Image + Send + Sync> {
+        &self.image
+    }
+
+    /// Level 0 is the image itself, every further one has half the size.
+    pub fn level(&self, level: usize) -> &(dyn Image + Send + Sync) {
+        match level {
+            0 => self.image.as_ref(),
+            _ => &self.smaller()[level - 1],
+        }
+    }
+
+    pub fn level_count(&self) -> usize {
+        1 + self.smaller().len()
+    }
+
+    /// Levels 1 and up. An empty image has none.
+    fn smaller(&self) -> &[MipLevel] {
+        self.smaller.get_or_init(|| {
+            let mut levels: Vec<MipLevel> = Vec::new();
+            loop {
+                let last = levels
+                    .last()
+                    .map_or(self.image.as_ref() as &dyn Image, |level| level);
+                if last.width() * last.height() <= 1 {
+                    break;
+                }
+                let next = MipLevel::half_of(last);
+                levels.push(next);
+            }
+            levels
+        })
+    }
+}
+
+struct MipLevel {
+    width: usize,
+    height: usize,
+    pixels: Vec<[f64; 3]>,
+}
+
+impl MipLevel {
+    /// Averages 2 x 2 texel blocks, repeating the last row or column of odd sizes.
+    fn half_of(image: &dyn Image) -> Self {
+        let width = image.width().div_ceil(2);
+        let height = image.height().div_ceil(2);
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let mut sum = [0.0; 3];
+                for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                    let texel = image.get(
+                        (2 * x + dx).min(image.width() - 1),
+                        (2 * y + dy).min(image.height() - 1),
+                    );
+                    for (channel, value) in sum.iter_mut().zip(texel) {
+                        *channel += value / 4.0;
+                    }
+                }
+                pixels.push(sum);
+            }
+        }
+        MipLevel {
+            width,
+            height,
+            pixels,
+        }
+    }
+}
+
+impl Image for MipLevel {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    fn get(&self, x: usize, y: usize) -> [f64; 3] {
+        self.pixels[y * self.width + x]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Blank(usize, usize);
+
+    impl Image for Blank {
+        fn width(&self) -> usize {
+            self.0
+        }
+
+        fn height(&self) -> usize {
+            self.1
+        }
+
+        fn get(&self, _: usize, _: usize) -> [f64; 3] {
+            [1.0; 3]
+        }
+    }
+
+    #[test]
+    fn halves_down_to_a_single_texel() {
+        let mipmap = MipMap::new(Arc::new(Blank(5, 2)));
+        assert_eq!(mipmap.level_count(), 4);
+        let sizes = (0..4).map(|i| (mipmap.level(i).width(), mipmap.level(i).height()));
+        assert_eq!(sizes.collect::<Vec<_>>(), [(5, 2), (3, 1), (2, 1), (1, 1)]);
+        assert_eq!(mipmap.level(3).get(0, 0), [1.0; 3]);
+    }
+
+    #[test]
+    fn keeps_empty_images_alone() {
+        for (width, height) in [(0, 0), (0, 4), (4, 0)] {
+            let mipmap = MipMap::new(Arc::new(Blank(width, height)));
+            assert_eq!(mipmap.level_count(), 1);
+            assert_eq!(mipmap.level(0).width(), width);
+        }
+    }
+}
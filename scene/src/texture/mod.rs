@@ -1,28 +1,245 @@
 use std::sync::Arc;
 
-use plain::DeserializablePlainTexture;
+use checker::CheckerTexture;
+use core::types::math::Vec3;
+use gradient::{GradientShape, GradientTexture};
+use jsonc::Value;
+use noise::{NoiseKind, NoiseTexture};
+use plain::{filter_from_str, wrap_from_str, DeserializablePlainTexture, Filter, Wrap};
+use transform::UvTransformTexture;
 use types::LDRColor;
 
-use crate::{ImageCache, ImageLoader};
+use crate::{
+    angle_from_json_value, direction_from_json_value, ldr_color_from_json_value,
+    position_from_json_value, AssetCache, ImageLoader,
+};
 
+pub mod checker;
+pub mod gradient;
+pub mod mipmap;
+pub mod noise;
 pub mod plain;
+pub mod transform;
+
+/// Where on a surface a texture is looked up.
+#[derive(Clone, Copy, Debug)]
+pub struct TexturePoint {
+    pub u: f64,
+    pub v: f64,
+    /// Relative to the position of the primitive.
+    pub object: Vec3,
+    pub world: Vec3,
+    /// Change of (u, v) across the pixel seen at the point, along the two
+    /// axes of its footprint. Zero when the footprint is unknown.
+    pub footprint: [(f64, f64); 2],
+}
 
 pub trait Texture {
-    fn get(&self, u: f64, v: f64) -> LDRColor;
+    fn get(&self, point: &TexturePoint) -> LDRColor;
+}
+
+/// Coordinates a procedural texture is evaluated in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Space {
+    /// The surface UV coordinates, as the point `(u, v, 0)`.
+    Uv,
+    Object,
+    World,
+}
+
+impl Space {
+    pub fn point(self, point: &TexturePoint) -> Vec3 {
+        match self {
+            Space::Uv => Vec3::new(point.u, point.v, 0.0),
+            Space::Object => point.object,
+            Space::World => point.world,
+        }
+    }
+}
+
+pub fn space_from_str(s: &str) -> Result<Space, String> {
+    match s {
+        "uv" => Ok(Space::Uv),
+        "object" => Ok(Space::Object),
+        "world" => Ok(Space::World),
+        _ => Err(format!("Unknown texture space: {}", s)),
+    }
 }
 
 #[derive(Clone, Debug)]
 pub enum DeserializableTexture {
     Plain(DeserializablePlainTexture),
+    Checker(CheckerTexture),
+    Noise(NoiseTexture),
+    Gradient(GradientTexture),
 }
 
 impl DeserializableTexture {
     pub fn into_texture<T: ImageLoader>(
         self,
-        image_cache: &mut ImageCache<T>,
-    ) -> Arc<dyn Texture + Send + Sync> {
+        assets: &mut AssetCache<T>,
+    ) -> Result<Arc<dyn Texture + Send + Sync>, String> {
         match self {
-            DeserializableTexture::Plain(t) => t.into_texture(image_cache),
+            DeserializableTexture::Plain(t) => t.into_texture(assets),
+            DeserializableTexture::Checker(t) => Ok(Arc::new(t)),
+            DeserializableTexture::Noise(t) => Ok(Arc::new(t)),
+            DeserializableTexture::Gradient(t) => Ok(Arc::new(t)),
         }
     }
 }
+
+/// Parse a texture such as `material.albedoTexture` and load its image.
+///
+/// Without a `type` the texture is an image, for compatibility. Any texture
+/// may move its UV coordinates with `uvScale`, `uvRotation` and `uvOffset`.
+pub fn from_json_value<T: ImageLoader>(
+    json: &Value,
+    assets: &mut AssetCache<T>,
+) -> Result<Arc<dyn Texture + Send + Sync>, String> {
+    let Value::Object(dict) = json else {
+        return Err("Texture must be a JSON object".to_string());
+    };
+    let type_str = match dict.get("type") {
+        Some(Value::String(s)) => s.as_str(),
+        Some(_) => return Err("Texture type must be a string".to_string()),
+        None => "image",
+    };
+
+    let texture = if type_str == "image" {
+        let Value::String(path) = dict.get("path").ok_or("Missing required field: path")? else {
+            return Err("Texture path must be a string".to_string());
+        };
+        // smooth is the older switch between nearest and filtered lookups
+        let filter = match (dict.get("filter"), dict.get("smooth")) {
+            (Some(Value::String(s)), _) => filter_from_str(s)?,
+            (Some(_), _) => return Err("filter must be a string".to_string()),
+            (None, Some(Value::Bool(false))) => Filter::Nearest,
+            (None, Some(Value::Bool(true)) | None) => Filter::Trilinear,
+            (None, Some(_)) => return Err("smooth must be a boolean".to_string()),
+        };
+        let border_color = match dict.get("borderColor") {
+            Some(json) => ldr_color_from_json_value(json)?,
+            None => LDRColor::new(0.0, 0.0, 0.0),
+        };
+        let wrap = match dict.get("wrap") {
+            Some(Value::String(s)) => wrap_from_str(s, border_color)?,
+            Some(_) => return Err("wrap must be a string".to_string()),
+            None => Wrap::Repeat,
+        };
+        DeserializableTexture::Plain(DeserializablePlainTexture::new(path.clone(), filter, wrap))
+    } else {
+        let space = match dict.get("space") {
+            Some(Value::String(s)) => space_from_str(s)?,
+            Some(_) => return Err("Texture space must be a string".to_string()),
+            None => Space::Uv,
+        };
+        let scale = match dict.get("scale") {
+            Some(Value::Number(s)) if *s > 0.0 => *s,
+            Some(_) => return Err("Texture scale must be a number greater than 0".to_string()),
+            None => 1.0,
+        };
+        let colors = match dict.get("colors") {
+            Some(Value::Array(array)) if array.len() == 2 => (
+                ldr_color_from_json_value(&array[0])?,
+                ldr_color_from_json_value(&array[1])?,
+            ),
+            Some(_) => return Err("Texture colors must be an array of 2 colors".to_string()),
+            None => (LDRColor::new(0.0, 0.0, 0.0), LDRColor::new(1.0, 1.0, 1.0)),
+        };
+
+        match type_str {
+            "checker" => DeserializableTexture::Checker(CheckerTexture::new(space, scale, colors)),
+            "noise" | "fbm" | "turbulence" | "marble" | "wood" => {
+                let kind = match type_str {
+                    "noise" => NoiseKind::Noise,
+                    "fbm" => NoiseKind::Fbm,
+                    "turbulence" => NoiseKind::Turbulence,
+                    "marble" => NoiseKind::Marble,
+                    _ => NoiseKind::Wood,
+                };
+                let octaves = match dict.get("octaves") {
+                    Some(Value::Number(n)) if *n >= 1.0 && n.fract() == 0.0 => *n as usize,
+                    Some(_) => return Err("octaves must be a positive integer".to_string()),
+                    None => 5,
+                };
+                let distortion = match dict.get("distortion") {
+                    Some(Value::Number(d)) if *d >= 0.0 => *d,
+                    Some(_) => return Err("distortion must be a non-negative number".to_string()),
+                    None => kind.default_distortion(),
+                };
+                let seed = match dict.get("seed") {
+                    Some(Value::Number(n)) if *n >= 0.0 && n.fract() == 0.0 => *n as u64,
+                    Some(_) => return Err("seed must be a non-negative integer".to_string()),
+                    None => 0,
+                };
+                DeserializableTexture::Noise(NoiseTexture::new(
+                    kind, space, scale, colors, octaves, distortion, seed,
+                ))
+            }
+            "linearGradient" => {
+                let direction = match dict.get("direction") {
+                    Some(json) => *direction_from_json_value(json)?,
+                    None => Vec3::new(1.0, 0.0, 0.0),
+                };
+                DeserializableTexture::Gradient(GradientTexture::new(
+                    GradientShape::Linear(direction),
+                    space,
+                    scale,
+                    colors,
+                ))
+            }
+            "radialGradient" => {
+                let center = match dict.get("center") {
+                    Some(json) => *position_from_json_value(json)?,
+                    None => Vec3::ZERO,
+                };
+                DeserializableTexture::Gradient(GradientTexture::new(
+                    GradientShape::Radial(center),
+                    space,
+                    scale,
+                    colors,
+                ))
+            }
+            _ => return Err(format!("Unknown texture type: {}", type_str)),
+        }
+    };
+
+    let scale = match dict.get("uvScale") {
+        Some(Value::Number(s)) => (*s, *s),
+        Some(Value::Array(array)) => match array.as_slice() {
+            [Value::Number(su), Value::Number(sv)] => (*su, *sv),
+            _ => return Err("uvScale must be a number or an array of 2 numbers".to_string()),
+        },
+        Some(_) => return Err("uvScale must be a number or an array of 2 numbers".to_string()),
+        None => (1.0, 1.0),
+    };
+    let offset = match dict.get("uvOffset") {
+        Some(Value::Array(array)) => match array.as_slice() {
+            [Value::Number(ou), Value::Number(ov)] => (*ou, *ov),
+            _ => return Err("uvOffset must be an array of 2 numbers".to_string()),
+        },
+        Some(_) => return Err("uvOffset must be an array of 2 numbers".to_string()),
+        None => (0.0, 0.0),
+    };
+    let rotation = match dict.get("uvRotation") {
+        Some(json) => angle_from_json_value(json)?,
+        None => 0.0,
+    };
+
+    let texture = texture.into_texture(assets)?;
+    if scale == (1.0, 1.0) && offset == (0.0, 0.0) && rotation == 0.0 {
+        Ok(texture)
+    } else {
+        Ok(Arc::new(UvTransformTexture::new(
+            texture, scale, rotation, offset,
+        )))
+    }
+}
+
+pub(crate) fn lerp_color(a: LDRColor, b: LDRColor, t: f64) -> LDRColor {
+    LDRColor {
+        r: a.r * (1.0 - t) + b.r * t,
+        g: a.g * (1.0 - t) + b.g * t,
+        b: a.b * (1.0 - t) + b.b * t,
+    }
+}
@@ -0,0 +1,185 @@
+use std::f64::consts::PI;
+
+use core::{random::Rng, types::math::Vec3};
+use types::LDRColor;
+
+use super::{lerp_color, Space, Texture, TexturePoint};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NoiseKind {
+    /// Plain Perlin noise.
+    Noise,
+    /// Fractional Brownian motion, octaves of noise at doubling frequencies.
+    Fbm,
+    /// Like fBm, summing the absolute value of each octave.
+    Turbulence,
+    /// Sine stripes along x, bent by turbulence.
+    Marble,
+    /// Rings around the y axis, bent by fBm.
+    Wood,
+}
+
+impl NoiseKind {
+    pub fn default_distortion(self) -> f64 {
+        match self {
+            NoiseKind::Marble => 5.0,
+            NoiseKind::Wood => 0.5,
+            _ => 0.0,
+        }
+    }
+}
+
+/// Textures built from Perlin noise, blending between two colors.
+#[derive(Clone, Debug)]
+pub struct NoiseTexture {
+    kind: NoiseKind,
+    space: Space,
+    /// Frequency of the first octave, in features per unit.
+    scale: f64,
+    colors: (LDRColor, LDRColor),
+    octaves: usize,
+    /// How strongly the noise bends the marble stripes or wood rings.
+    distortion: f64,
+    perlin: Perlin,
+}
+
+impl NoiseTexture {
+    pub fn new(
+        kind: NoiseKind,
+        space: Space,
+        scale: f64,
+        colors: (LDRColor, LDRColor),
+        octaves: usize,
+        distortion: f64,
+        seed: u64,
+    ) -> Self {
+        NoiseTexture {
+            kind,
+            space,
+            scale,
+            colors,
+            octaves,
+            distortion,
+            perlin: Perlin::new(seed),
+        }
+    }
+
+    /// Sum of the octaves, normalized to [-1, 1] (or [0, 1] for `absolute`).
+    fn octaves(&self, p: Vec3, absolute: bool) -> f64 {
+        let mut sum = 0.0;
+        let mut weight = 0.0;
+        let mut amplitude = 1.0;
+        let mut frequency = 1.0;
+        for _ in 0..self.octaves {
+            let noise = self.perlin.noise(p * frequency);
+            sum += amplitude * if absolute { noise.abs() } else { noise };
+            weight += amplitude;
+            amplitude *= 0.5;
+            frequency *= 2.0;
+        }
+        sum / weight
+    }
+}
+
+impl Texture for NoiseTexture {
+    fn get(&self, point: &TexturePoint) -> LDRColor {
+        let p = self.space.point(point) * self.scale;
+        let t = match self.kind {
+            NoiseKind::Noise => 0.5 + 0.5 * self.perlin.noise(p),
+            NoiseKind::Fbm => 0.5 + 0.5 * self.octaves(p, false),
+            NoiseKind::Turbulence => self.octaves(p, true),
+            NoiseKind::Marble => {
+                0.5 + 0.5 * ((p.x + self.distortion * self.octaves(p, true)) * PI).sin()
+            }
+            NoiseKind::Wood => {
+                let radius = (p.x * p.x + p.z * p.z).sqrt();
+                (radius + self.distortion * self.octaves(p, false)).rem_euclid(1.0)
+            }
+        };
+        lerp_color(self.colors.0, self.colors.1, t.clamp(0.0, 1.0))
+    }
+}
+
+/// Ken Perlin's improved noise, with a permutation shuffled from a seed.
+#[derive(Clone, Debug)]
+struct Perlin {
+    /// The permutation twice over, so lookups don't need to wrap.
+    permutation: Vec<u8>,
+}
+
+impl Perlin {
+    fn new(seed: u64) -> Self {
+        let mut permutation: Vec<u8> = (0..=255).collect();
+        let mut rng = Rng::new(seed);
+        for i in (1..permutation.len()).rev() {
+            let j = (rng.next_u64() % (i as u64 + 1)) as usize;
+            permutation.swap(i, j);
+        }
+        permutation.extend_from_within(..);
+        Perlin { permutation }
+    }
+
+    /// Smooth noise in about [-1, 1], zero at integer lattice points.
+    fn noise(&self, p: Vec3) -> f64 {
+        let cell = |x: f64| (x.floor() as i64).rem_euclid(256) as usize;
+        let (xi, yi, zi) = (cell(p.x), cell(p.y), cell(p.z));
+        let (x, y, z) = (p.x - p.x.floor(), p.y - p.y.floor(), p.z - p.z.floor());
+        let (u, v, w) = (fade(x), fade(y), fade(z));
+
+        let perm = |i: usize| self.permutation[i] as usize;
+        let a = perm(xi) + yi;
+        let aa = perm(a) + zi;
+        let ab = perm(a + 1) + zi;
+        let b = perm(xi + 1) + yi;
+        let ba = perm(b) + zi;
+        let bb = perm(b + 1) + zi;
+
+        lerp(
+            lerp(
+                lerp(grad(perm(aa), x, y, z), grad(perm(ba), x - 1.0, y, z), u),
+                lerp(
+                    grad(perm(ab), x, y - 1.0, z),
+                    grad(perm(bb), x - 1.0, y - 1.0, z),
+                    u,
+                ),
+                v,
+            ),
+            lerp(
+                lerp(
+                    grad(perm(aa + 1), x, y, z - 1.0),
+                    grad(perm(ba + 1), x - 1.0, y, z - 1.0),
+                    u,
+                ),
+                lerp(
+                    grad(perm(ab + 1), x, y - 1.0, z - 1.0),
+                    grad(perm(bb + 1), x - 1.0, y - 1.0, z - 1.0),
+                    u,
+                ),
+                v,
+            ),
+            w,
+        )
+    }
+}
+
+fn fade(t: f64) -> f64 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(a: f64, b: f64, t: f64) -> f64 {
+    a + (b - a) * t
+}
+
+/// Dot product with one of 12 gradient directions picked by the hash.
+fn grad(hash: usize, x: f64, y: f64, z: f64) -> f64 {
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = if h < 4 {
+        y
+    } else if h == 12 || h == 14 {
+        x
+    } else {
+        z
+    };
+    (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
@@ -2,80 +2,255 @@ use std::sync::Arc;
 
 use types::LDRColor;
 
-use crate::{Image, ImageCache, ImageLoader};
+use crate::{AssetCache, Image, ImageLoader};
 
-use super::Texture;
+use super::{mipmap::MipMap, Texture, TexturePoint};
+
+/// What an image shows outside [0, 1].
+#[derive(Clone, Copy, Debug)]
+pub enum Wrap {
+    Repeat,
+    /// Repeats, flipping every other copy.
+    Mirror,
+    /// Stretches the edge texels.
+    Clamp,
+    /// A constant color.
+    Border(LDRColor),
+}
+
+pub fn wrap_from_str(s: &str, border_color: LDRColor) -> Result<Wrap, String> {
+    match s {
+        "repeat" => Ok(Wrap::Repeat),
+        "mirror" => Ok(Wrap::Mirror),
+        "clamp" => Ok(Wrap::Clamp),
+        "border" => Ok(Wrap::Border(border_color)),
+        _ => Err(format!("Unknown wrap mode: {}", s)),
+    }
+}
+
+impl Wrap {
+    /// Maps a texel index along an axis of `size` texels into the image,
+    /// or None for the border.
+    fn index(self, i: i64, size: usize) -> Option<usize> {
+        let size = size as i64;
+        let i = match self {
+            Wrap::Repeat => i.rem_euclid(size),
+            Wrap::Mirror => {
+                let i = i.rem_euclid(2 * size);
+                if i < size {
+                    i
+                } else {
+                    2 * size - 1 - i
+                }
+            }
+            Wrap::Clamp => i.clamp(0, size - 1),
+            Wrap::Border(_) if i < 0 || i >= size => return None,
+            Wrap::Border(_) => i,
+        };
+        Some(i as usize)
+    }
+}
+
+/// How texels are combined into a lookup.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Filter {
+    /// The texel under the point.
+    Nearest,
+    /// The four texels around the point, at full resolution.
+    Bilinear,
+    /// Bilinear lookups in the two mip levels closest to the pixel footprint.
+    Trilinear,
+    /// Trilinear lookups along the longer axis of the footprint, which stays
+    /// sharp where surfaces are seen at grazing angles.
+    Anisotropic,
+}
+
+pub fn filter_from_str(s: &str) -> Result<Filter, String> {
+    match s {
+        "nearest" => Ok(Filter::Nearest),
+        "bilinear" => Ok(Filter::Bilinear),
+        "trilinear" => Ok(Filter::Trilinear),
+        "anisotropic" => Ok(Filter::Anisotropic),
+        _ => Err(format!("Unknown texture filter: {}", s)),
+    }
+}
+
+/// Most lookups an anisotropic filter makes along the footprint.
+const MAX_ANISOTROPY: f64 = 16.0;
 
 #[derive(Clone, Debug)]
 pub struct DeserializablePlainTexture {
     path: String,
-    smooth: bool,
+    filter: Filter,
+    wrap: Wrap,
 }
 
 impl DeserializablePlainTexture {
+    pub fn new(path: String, filter: Filter, wrap: Wrap) -> Self {
+        DeserializablePlainTexture { path, filter, wrap }
+    }
+
     pub fn into_texture<T: ImageLoader>(
         self,
-        image_cache: &mut ImageCache<T>,
-    ) -> Arc<dyn Texture + Send + Sync> {
-        if self.smooth {
-            Arc::new(PlainLinearTexture {
-                image: image_cache.load(&self.path),
-            })
-        } else {
-            Arc::new(PlainNearestTexture {
-                image: image_cache.load(&self.path),
-            })
+        assets: &mut AssetCache<T>,
+    ) -> Result<Arc<dyn Texture + Send + Sync>, String> {
+        let image = WrappedImage {
+            mipmap: assets.load_mipmap(&self.path)?,
+            wrap: self.wrap,
+        };
+        Ok(match self.filter {
+            Filter::Nearest => Arc::new(PlainNearestTexture { image }),
+            Filter::Bilinear => Arc::new(PlainLinearTexture { image }),
+            Filter::Trilinear => Arc::new(PlainTrilinearTexture { image }),
+            Filter::Anisotropic => Arc::new(PlainAnisotropicTexture { image }),
+        })
+    }
+}
+
+/// Texel lookup with a wrap mode. Texel (x, y) of a level covers
+/// [x, x + 1) / width horizontally and [y, y + 1) / height vertically.
+struct WrappedImage {
+    mipmap: Arc<MipMap>,
+    wrap: Wrap,
+}
+
+impl WrappedImage {
+    fn texel(&self, image: &dyn Image, x: i64, y: i64) -> [f64; 3] {
+        match (
+            self.wrap.index(x, image.width()),
+            self.wrap.index(y, image.height()),
+        ) {
+            (Some(x), Some(y)) => image.get(x, y),
+            _ => match self.wrap {
+                Wrap::Border(color) => [color.r, color.g, color.b],
+                _ => unreachable!("only borders leave the image"),
+            },
+        }
+    }
+
+    fn nearest(&self, u: f64, v: f64) -> [f64; 3] {
+        let image = self.mipmap.level(0);
+        let x = (u * image.width() as f64).floor() as i64;
+        let y = (v * image.height() as f64).floor() as i64;
+        self.texel(image, x, y)
+    }
+
+    fn bilinear(&self, level: usize, u: f64, v: f64) -> [f64; 3] {
+        let image = self.mipmap.level(level);
+        // interpolate between the centers of the four nearest texels
+        let x = u * image.width() as f64 - 0.5;
+        let y = v * image.height() as f64 - 0.5;
+
+        let x0 = x.floor() as i64;
+        let y0 = y.floor() as i64;
+
+        let dx = x - x.floor();
+        let dy = y - y.floor();
+
+        let c00 = self.texel(image, x0, y0);
+        let c10 = self.texel(image, x0 + 1, y0);
+        let c01 = self.texel(image, x0, y0 + 1);
+        let c11 = self.texel(image, x0 + 1, y0 + 1);
+
+        std::array::from_fn(|i| lerp(lerp(c00[i], c10[i], dx), lerp(c01[i], c11[i], dx), dy))
+    }
+
+    /// Bilinear lookup blended between the levels around the fractional `level`.
+    fn trilinear(&self, level: f64, u: f64, v: f64) -> [f64; 3] {
+        let level = level.clamp(0.0, (self.mipmap.level_count() - 1) as f64);
+        let lower = level.floor() as usize;
+        let t = level - level.floor();
+        let color = self.bilinear(lower, u, v);
+        if t == 0.0 {
+            return color;
         }
+        let upper = self.bilinear(lower + 1, u, v);
+        std::array::from_fn(|i| lerp(color[i], upper[i], t))
+    }
+
+    /// Length of a UV offset in full resolution texels.
+    fn texels(&self, (du, dv): (f64, f64)) -> f64 {
+        let image = self.mipmap.image();
+        (du * image.width() as f64).hypot(dv * image.height() as f64)
     }
 }
 
 struct PlainNearestTexture {
-    image: Arc<dyn Image + Send + Sync>,
+    image: WrappedImage,
 }
 
 impl Texture for PlainNearestTexture {
-    fn get(&self, u: f64, v: f64) -> LDRColor {
-        let width = self.image.width();
-        let height = self.image.height();
-
-        let x = (u * width as f64).round() as usize % width;
-        let y = (v * height as f64).round() as usize % height;
-
-        let (r, g, b) = self.image.get(y, x).into();
+    fn get(&self, point: &TexturePoint) -> LDRColor {
+        let (r, g, b) = self.image.nearest(point.u, point.v).into();
 
         LDRColor::new(r, g, b)
     }
 }
 
 struct PlainLinearTexture {
-    image: Arc<dyn Image + Send + Sync>,
+    image: WrappedImage,
 }
 
 impl Texture for PlainLinearTexture {
-    fn get(&self, u: f64, v: f64) -> LDRColor {
-        let width = self.image.width() as f64;
-        let height = self.image.height() as f64;
+    fn get(&self, point: &TexturePoint) -> LDRColor {
+        let (r, g, b) = self.image.bilinear(0, point.u, point.v).into();
 
-        let x = u * (width - 1.0);
-        let y = v * (height - 1.0);
+        LDRColor::new(r, g, b)
+    }
+}
 
-        let x0 = x.floor() as usize;
-        let y0 = y.floor() as usize;
+struct PlainTrilinearTexture {
+    image: WrappedImage,
+}
 
-        let dx = x - x.floor();
-        let dy = y - y.floor();
+impl Texture for PlainTrilinearTexture {
+    fn get(&self, point: &TexturePoint) -> LDRColor {
+        // the level where the longer footprint axis spans one texel
+        let [a, b] = point.footprint;
+        let size = self.image.texels(a).max(self.image.texels(b));
+        let level = size.log2().max(0.0);
 
-        let x1 = (x0 + 1).min(self.image.width() - 1);
-        let y1 = (y0 + 1).min(self.image.height() - 1);
+        let (r, g, b) = self.image.trilinear(level, point.u, point.v).into();
 
-        let c00 = self.image.get(y0, x0);
-        let c10 = self.image.get(y0, x1);
-        let c01 = self.image.get(y1, x0);
-        let c11 = self.image.get(y1, x1);
+        LDRColor::new(r, g, b)
+    }
+}
+
+struct PlainAnisotropicTexture {
+    image: WrappedImage,
+}
+
+impl Texture for PlainAnisotropicTexture {
+    fn get(&self, point: &TexturePoint) -> LDRColor {
+        let [a, b] = point.footprint;
+        let (major, minor) = if self.image.texels(a) >= self.image.texels(b) {
+            (a, b)
+        } else {
+            (b, a)
+        };
+        let major_size = self.image.texels(major);
+        let minor_size = self.image.texels(minor);
+
+        // lookups spread along the major axis, each as wide as the minor one
+        let count = if minor_size > 0.0 {
+            (major_size / minor_size).ceil().clamp(1.0, MAX_ANISOTROPY)
+        } else {
+            MAX_ANISOTROPY
+        };
+        let level = (major_size / count).log2().max(0.0);
+
+        let mut color = [0.0; 3];
+        for i in 0..count as usize {
+            let t = (i as f64 + 0.5) / count - 0.5;
+            let sample = self
+                .image
+                .trilinear(level, point.u + major.0 * t, point.v + major.1 * t);
+            for (channel, value) in color.iter_mut().zip(sample) {
+                *channel += value / count;
+            }
+        }
 
-        let r = lerp(lerp(c00[0], c10[0], dx), lerp(c01[0], c11[0], dx), dy);
-        let g = lerp(lerp(c00[1], c10[1], dx), lerp(c01[1], c11[1], dx), dy);
-        let b = lerp(lerp(c00[2], c10[2], dx), lerp(c01[2], c11[2], dx), dy);
+        let (r, g, b) = color.into();
 
         LDRColor::new(r, g, b)
     }
@@ -84,3 +259,63 @@ impl Texture for PlainLinearTexture {
 fn lerp(a: f64, b: f64, t: f64) -> f64 {
     a * (1.0 - t) + b * t
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2 texels wide and 3 tall, each holding its own coordinates.
+    struct Grid;
+
+    impl Image for Grid {
+        fn width(&self) -> usize {
+            2
+        }
+
+        fn height(&self) -> usize {
+            3
+        }
+
+        fn get(&self, x: usize, y: usize) -> [f64; 3] {
+            assert!(x < 2 && y < 3, "texel ({}, {}) is outside the image", x, y);
+            [x as f64, y as f64, 0.0]
+        }
+    }
+
+    fn wrapped(wrap: Wrap) -> WrappedImage {
+        WrappedImage {
+            mipmap: Arc::new(MipMap::new(Arc::new(Grid))),
+            wrap,
+        }
+    }
+
+    #[test]
+    fn addresses_texels_by_column_and_row() {
+        let image = wrapped(Wrap::Clamp);
+        assert_eq!(image.nearest(0.25, 0.1), [0.0, 0.0, 0.0]);
+        assert_eq!(image.nearest(0.75, 0.5), [1.0, 1.0, 0.0]);
+        assert_eq!(image.nearest(0.25, 0.9), [0.0, 2.0, 0.0]);
+        // halfway between both columns and the first two rows
+        assert_eq!(image.bilinear(0, 0.5, 1.0 / 3.0), [0.5, 0.5, 0.0]);
+    }
+
+    #[test]
+    fn wraps_outside_the_image() {
+        let border = LDRColor::new(0.25, 0.5, 0.75);
+        // at v = 0.5 both samplers land on the center of row 1, and at these
+        // u on the center of a texel one column outside the image
+        for (wrap, left, right) in [
+            (Wrap::Repeat, Some(1.0), Some(0.0)),
+            (Wrap::Mirror, Some(0.0), Some(1.0)),
+            (Wrap::Clamp, Some(0.0), Some(1.0)),
+            (Wrap::Border(border), None, None),
+        ] {
+            let image = wrapped(wrap);
+            for (u, x) in [(-0.25, left), (1.25, right)] {
+                let expected = x.map_or([0.25, 0.5, 0.75], |x| [x, 1.0, 0.0]);
+                assert_eq!(image.nearest(u, 0.5), expected, "{:?} at {}", wrap, u);
+                assert_eq!(image.bilinear(0, u, 0.5), expected, "{:?} at {}", wrap, u);
+            }
+        }
+    }
+}
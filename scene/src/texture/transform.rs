@@ -0,0 +1,51 @@
+use std::sync::Arc;
+
+use types::LDRColor;
+
+use super::{Texture, TexturePoint};
+
+/// Moves the UV coordinates of another texture: scaled, then rotated
+/// counterclockwise around the origin, then offset.
+pub struct UvTransformTexture {
+    texture: Arc<dyn Texture + Send + Sync>,
+    scale: (f64, f64),
+    rotation: f64,
+    offset: (f64, f64),
+}
+
+impl UvTransformTexture {
+    pub fn new(
+        texture: Arc<dyn Texture + Send + Sync>,
+        scale: (f64, f64),
+        rotation: f64,
+        offset: (f64, f64),
+    ) -> Self {
+        UvTransformTexture {
+            texture,
+            scale,
+            rotation,
+            offset,
+        }
+    }
+}
+
+impl UvTransformTexture {
+    /// Scales and rotates, without the offset.
+    fn linear(&self, (u, v): (f64, f64)) -> (f64, f64) {
+        let (u, v) = (u * self.scale.0, v * self.scale.1);
+        let (sin, cos) = self.rotation.sin_cos();
+        (u * cos - v * sin, u * sin + v * cos)
+    }
+}
+
+impl Texture for UvTransformTexture {
+    fn get(&self, point: &TexturePoint) -> LDRColor {
+        let (u, v) = self.linear((point.u, point.v));
+        self.texture.get(&TexturePoint {
+            u: u + self.offset.0,
+            v: v + self.offset.1,
+            footprint: point.footprint.map(|axis| self.linear(axis)),
+            ..*point
+        })
+    }
+}